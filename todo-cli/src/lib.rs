@@ -0,0 +1,1745 @@
+//! Задачи ToDo: модель, список с операциями над ним и хранение в JSON
+
+use chrono::{DateTime, Days, Local, Months, NaiveDate, NaiveDateTime, TimeDelta}; // Работа с датами/временем
+use serde::{Deserialize, Serialize}; // Сериализация/десериализация
+use std::cmp::Reverse; // Сортировка по убыванию
+use std::fs::{File, OpenOptions}; // Работа с файлами
+use std::io::{self, Read, Write}; // Ввод/вывод
+use std::path::{Path, PathBuf}; // Работа с путями
+
+pub const DATA_FILE: &str = "zadaniey.json"; // Файл с задачами по умолчанию
+
+/// Стили ANSI
+pub const RED: &str = "31";
+pub const GREEN_DIM: &str = "2;32";
+pub const BOLD_YELLOW: &str = "1;33";
+
+/// Раскраска текста стилем ANSI ("31" — красный и т.д.), если цвета включены
+pub fn paint(text: &str, style: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Источник текущего времени (подменяется для детерминированных проверок)
+pub trait Clock {
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// Настоящие системные часы
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Поддельные часы, всегда возвращающие заданный момент
+pub struct FakeClock {
+    pub now: DateTime<Local>,
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Local> {
+        self.now
+    }
+}
+
+/// Приоритет задачи (порядок вариантов задаёт порядок сравнения)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prioritet {
+    Nizkiy,
+    #[default]
+    Sredniy,
+    Vysokiy,
+}
+
+impl Prioritet {
+    /// Разбор метки приоритета вида `!высокий`
+    pub fn parse_token(token: &str) -> Option<Self> {
+        match token {
+            "!низкий" => Some(Prioritet::Nizkiy),
+            "!средний" => Some(Prioritet::Sredniy),
+            "!высокий" => Some(Prioritet::Vysokiy),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Prioritet::Nizkiy => "низкий",
+            Prioritet::Sredniy => "средний",
+            Prioritet::Vysokiy => "высокий",
+        }
+    }
+}
+
+/// Отделение метки приоритета в начале текста: "!высокий купить молоко"
+pub fn split_priority(text: &str) -> (Prioritet, String) {
+    // Метка может быть и единственным словом: тогда текст пуст
+    let (token, rest) = text.split_once(' ').unwrap_or((text, ""));
+    match Prioritet::parse_token(token) {
+        Some(priority) => (priority, rest.trim().to_string()),
+        None => (Prioritet::default(), text.to_string()),
+    }
+}
+
+/// Отделение меток "#слово" от текста: "позвонить #работа" → (["работа"], "позвонить")
+pub fn split_tags(text: &str) -> (Vec<String>, String) {
+    let mut tags: Vec<String> = Vec::new();
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => {
+                let tag = tag.to_lowercase();
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            _ => words.push(word),
+        }
+    }
+    (tags, words.join(" "))
+}
+
+/// Отделение правила повтора в конце текста: "полить цветы каждую неделю"
+pub fn split_recurrence(text: &str) -> (Option<&'static str>, String) {
+    for (suffix, rule) in [
+        ("каждый день", "daily"),
+        ("каждую неделю", "weekly"),
+        ("каждый месяц", "monthly"),
+    ] {
+        if let Some(rest) = text.strip_suffix(suffix) {
+            if rest.ends_with(' ') {
+                return (Some(rule), rest.trim_end().to_string());
+            }
+        }
+    }
+    (None, text.to_string())
+}
+
+/// Текст задачи без служебных частей: приоритета, меток и правила повтора
+fn bare_text(text: &str) -> String {
+    let (_, text) = split_priority(text.trim());
+    let (_, text) = split_tags(&text);
+    let (_, text) = split_recurrence(&text);
+    text.trim().to_string()
+}
+
+/// Задача без текста (одни пробелы или метки) бесполезна
+fn check_not_empty(text: &str) -> Result<(), String> {
+    if bare_text(text).is_empty() {
+        return Err("❌ Текст задачи не может быть пустым".to_string());
+    }
+    Ok(())
+}
+
+/// Структура задачи с автоматической сериализацией
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Zadanie {
+    pub id: usize,                             // Уникальный числовой идентификатор
+    pub text: String,                          // Текст задачи
+    pub done: bool,                            // Статус выполнения (true/false)
+    pub created_at: DateTime<Local>,           // Дата и время создания
+    pub completed_at: Option<DateTime<Local>>, // Дата выполнения (None если не выполнена)
+    pub due_date: Option<NaiveDate>,           // Срок выполнения (опционально)
+    #[serde(default)]
+    pub recur: Option<String>, // Правило повтора: "daily", "weekly" или "monthly"
+    #[serde(default)]
+    pub istochnik: Option<String>, // Как задача была создана (интерактив, rpc, ...)
+    #[serde(default)]
+    pub otlozheno_do: Option<NaiveDate>, // Скрыта из списка до этой даты
+    #[serde(default)]
+    pub priority: Prioritet, // Приоритет (в старых файлах — средний)
+    #[serde(default)]
+    pub tags: Vec<String>, // Метки без символа "#"
+    #[serde(default)]
+    pub note: Option<String>, // Заметка: подробности, ссылки
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_created_at: Option<String>, // Нераспознанная дата создания из старого файла
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_completed_at: Option<String>, // Нераспознанная дата выполнения из старого файла
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_due_date: Option<String>, // Нераспознанный срок из старого файла
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_otlozheno_do: Option<String>, // Нераспознанная дата откладывания из старого файла
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_copy: Option<usize>, // ID следующего повторения, созданного при выполнении
+}
+
+/// Задача в старом формате, где все даты хранились строками
+#[derive(Debug, Deserialize)]
+struct StarayaZadanie {
+    id: usize,
+    text: String,
+    done: bool,
+    created_at: String,
+    completed_at: Option<String>,
+    due_date: Option<String>,
+    #[serde(default)]
+    recur: Option<String>,
+    #[serde(default)]
+    istochnik: Option<String>,
+    #[serde(default)]
+    otlozheno_do: Option<String>,
+    #[serde(default)]
+    priority: Prioritet,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+impl StarayaZadanie {
+    /// Перевод в типизированные даты; нераспознанные значения не роняют загрузку,
+    /// а сохраняются как есть, чтобы их показала "проверка"
+    pub fn migrate(self) -> Zadanie {
+        // Пара (разобранная дата, исходная строка, если разобрать не удалось)
+        let date = |value: Option<String>| match value {
+            Some(value) => match NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                Ok(parsed) => (Some(parsed), None),
+                Err(_) => (None, Some(value)),
+            },
+            None => (None, None),
+        };
+        let (due_date, raw_due_date) = date(self.due_date);
+        let (otlozheno_do, raw_otlozheno_do) = date(self.otlozheno_do);
+
+        // Без даты создания подставляем начало эпохи, чтобы не выдумывать время;
+        // исходная строка остаётся в raw_created_at
+        let (created_at, raw_created_at) = match parse_timestamp(&self.created_at) {
+            Some(parsed) => (parsed, None),
+            None => (
+                DateTime::<Local>::from(std::time::UNIX_EPOCH),
+                Some(self.created_at),
+            ),
+        };
+        let (completed_at, raw_completed_at) = match self.completed_at {
+            Some(value) => match parse_timestamp(&value) {
+                Some(parsed) => (Some(parsed), None),
+                None => (None, Some(value)),
+            },
+            None => (None, None),
+        };
+
+        Zadanie {
+            id: self.id,
+            text: self.text,
+            done: self.done,
+            created_at,
+            completed_at,
+            due_date,
+            recur: self.recur,
+            istochnik: self.istochnik,
+            otlozheno_do,
+            priority: self.priority,
+            tags: self.tags,
+            note: self.note,
+            raw_created_at,
+            raw_completed_at,
+            raw_due_date,
+            raw_otlozheno_do,
+            next_copy: None,
+        }
+    }
+}
+
+pub const ISTOCHNIK_INTERAKTIV: &str = "интерактив"; // Команда в интерактивном меню
+pub const ISTOCHNIK_RPC: &str = "rpc"; // Команда JSON-режима
+pub const ISTOCHNIK_SKRIPT: &str = "скрипт"; // Разовая команда из аргументов запуска
+
+/// Сдвиг даты на один интервал правила повтора
+pub fn advance_by_rule(date: NaiveDate, rule: &str) -> Option<NaiveDate> {
+    match rule {
+        "daily" => date.checked_add_days(Days::new(1)),
+        "weekly" => date.checked_add_days(Days::new(7)),
+        "monthly" => date.checked_add_months(Months::new(1)),
+        _ => None, // Неизвестное правило
+    }
+}
+
+/// Человекочитаемое название правила повтора
+pub fn rule_label(rule: &str) -> &str {
+    match rule {
+        "daily" => "каждый день",
+        "weekly" => "каждую неделю",
+        "monthly" => "каждый месяц",
+        other => other,
+    }
+}
+
+impl Zadanie {
+    /// Создание новой задачи без срока выполнения
+    pub fn new(id: usize, text: String, clock: &dyn Clock) -> Self {
+        Zadanie {
+            id,
+            text,
+            done: false,                    // По умолчанию не выполнена
+            created_at: clock.now(),        // Текущая дата/время
+            completed_at: None,             // Пока нет даты выполнения
+            due_date: None,                 // Срок не установлен
+            recur: None,                    // Без повтора
+            istochnik: None,                // Источник проставляет список
+            otlozheno_do: None,             // Не отложена
+            priority: Prioritet::default(), // Средний приоритет
+            tags: Vec::new(),               // Без меток
+            note: None,                     // Без заметки
+            raw_created_at: None,           // Даты заданы часами
+            raw_completed_at: None,
+            raw_due_date: None,
+            raw_otlozheno_do: None,
+            next_copy: None,
+        }
+    }
+
+    /// Создание задачи с возможным сроком выполнения
+    pub fn with_due_date(
+        id: usize,
+        text: String,
+        due_date: Option<NaiveDate>,
+        clock: &dyn Clock,
+    ) -> Self {
+        Zadanie {
+            id,
+            text,
+            done: false,
+            created_at: clock.now(),
+            completed_at: None,
+            due_date, // Устанавливаем переданный срок
+            recur: None,
+            istochnik: None,
+            otlozheno_do: None,
+            priority: Prioritet::default(),
+            tags: Vec::new(),
+            note: None,
+            raw_created_at: None,
+            raw_completed_at: None,
+            raw_due_date: None,
+            raw_otlozheno_do: None,
+            next_copy: None,
+        }
+    }
+
+    /// Отметка задачи как выполненной
+    pub fn complete(&mut self, clock: &dyn Clock) {
+        self.done = true;
+        self.completed_at = Some(clock.now()); // Фиксируем время выполнения
+        self.raw_completed_at = None;
+    }
+
+    /// Возврат задачи в невыполненные
+    pub fn reopen(&mut self) {
+        self.done = false;
+        self.completed_at = None; // Время выполнения больше не актуально
+        self.raw_completed_at = None;
+    }
+
+    /// Просрочена ли задача: не выполнена и срок раньше сегодняшнего дня
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        if self.done {
+            return false;
+        }
+        self.due_date.is_some_and(|due| due < today)
+    }
+
+    /// Текстовые поля задачи с подписями для поиска
+    pub fn text_fields(&self) -> Vec<(&'static str, &str)> {
+        let mut fields = vec![("текст", self.text.as_str())];
+        fields.extend(self.tags.iter().map(|tag| ("тег", tag.as_str())));
+        if let Some(note) = &self.note {
+            fields.push(("заметка", note.as_str()));
+        }
+        fields
+    }
+
+    /// Нераспознанные при миграции даты с подписями
+    pub fn raw_dates(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("дата создания", &self.raw_created_at),
+            ("дата выполнения", &self.raw_completed_at),
+            ("срок", &self.raw_due_date),
+            ("дата откладывания", &self.raw_otlozheno_do),
+        ]
+        .into_iter()
+        .filter_map(|(label, raw)| raw.as_deref().map(|raw| (label, raw)))
+        .collect()
+    }
+
+    /// Отложена ли задача: дата, до которой она скрыта, ещё не наступила
+    pub fn is_snoozed(&self, today: NaiveDate) -> bool {
+        self.otlozheno_do.is_some_and(|until| until > today)
+    }
+
+    /// Назначена ли невыполненная задача на указанный день
+    pub fn is_due_on(&self, day: NaiveDate) -> bool {
+        !self.done && self.due_date == Some(day)
+    }
+
+    /// Следующий ожидаемый срок повторяющейся задачи (от текущего срока или от сегодня)
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let rule = self.recur.as_deref()?;
+        let base = self.due_date.unwrap_or(today);
+        advance_by_rule(base, rule)
+    }
+
+    /// Строка списка с отметкой просрочки и цветом по состоянию задачи
+    pub fn colored_line(&self, today: NaiveDate, color: bool) -> String {
+        if self.is_overdue(today) {
+            let line = format!("{} ⚠️ ПРОСРОЧЕНО", self.format_line());
+            paint(&line, RED, color)
+        } else if self.done {
+            paint(&self.format_line(), GREEN_DIM, color)
+        } else if self.priority == Prioritet::Vysokiy {
+            paint(&self.format_line(), BOLD_YELLOW, color)
+        } else {
+            self.format_line()
+        }
+    }
+
+    /// Строка задачи в общем формате: "ID [✓] Текст [срок: ...]"
+    pub fn format_line(&self) -> String {
+        let status = if self.done { "✓" } else { " " }; // Галочка для выполненных
+        let due_info = match &self.due_date {
+            Some(date) => format!(" [срок: {}]", date), // Показываем срок если есть
+            None => String::new(),
+        };
+        let marker = if self.priority == Prioritet::Vysokiy {
+            "(!) " // Отметка высокого приоритета
+        } else {
+            ""
+        };
+        let tags: String = self.tags.iter().map(|tag| format!(" #{}", tag)).collect();
+        let note = if self.note.is_some() { " 📝" } else { "" }; // Полный текст — в "показать"
+        format!(
+            "{:3} [{}] {}{}{}{}{}",
+            self.id, status, marker, self.text, tags, note, due_info
+        )
+    }
+}
+
+/// Ошибки операций со списком задач
+#[derive(Debug)]
+pub enum ToDoError {
+    NeNaydena(usize), // Задача с таким ID отсутствует
+}
+
+impl std::fmt::Display for ToDoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ToDoError::NeNaydena(id) => write!(f, "❌ Задача с ID {} не найдена", id),
+        }
+    }
+}
+
+/// Фильтр для выборки задач
+#[derive(Debug, Clone, PartialEq)]
+pub enum FiltrSpiska {
+    Vse,               // Все задачи
+    Vypolnennye,       // Только выполненные
+    Aktivnye,          // Только невыполненные
+    Prosrochennye,     // Невыполненные с истёкшим сроком
+    Istochnik(String), // Созданные указанным способом
+    Tekushchie,        // Все, кроме отложенных на будущее
+    Otlozhennye,       // Только отложенные на будущее
+    Teg(String),       // С указанной меткой
+}
+
+impl FiltrSpiska {
+    /// Разбор фильтра из слова команды
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "все" | "всё" => Some(FiltrSpiska::Vse),
+            "выполненные" => Some(FiltrSpiska::Vypolnennye),
+            "активные" => Some(FiltrSpiska::Aktivnye),
+            "просроченные" => Some(FiltrSpiska::Prosrochennye),
+            // Метка: "#работа"
+            _ => match word.strip_prefix('#') {
+                Some(tag) if !tag.is_empty() => Some(FiltrSpiska::Teg(tag.to_lowercase())),
+                _ => None,
+            },
+        }
+    }
+
+    /// Подходит ли задача под фильтр
+    pub fn matches(&self, zadanie: &Zadanie, today: NaiveDate) -> bool {
+        match self {
+            FiltrSpiska::Vse => true,
+            FiltrSpiska::Vypolnennye => zadanie.done,
+            FiltrSpiska::Aktivnye => !zadanie.done,
+            FiltrSpiska::Prosrochennye => zadanie.is_overdue(today),
+            FiltrSpiska::Istochnik(source) => zadanie.istochnik.as_deref() == Some(source),
+            FiltrSpiska::Tekushchie => !zadanie.is_snoozed(today),
+            FiltrSpiska::Otlozhennye => zadanie.is_snoozed(today),
+            FiltrSpiska::Teg(tag) => zadanie.tags.contains(tag),
+        }
+    }
+}
+
+/// Разбор отметки времени: старый формат `Local::now().to_string()` или RFC 3339
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .or_else(|_| DateTime::parse_from_rfc3339(value))
+        .ok()
+        .map(|dt| dt.with_timezone(&Local))
+}
+
+/// Разбор срока, введённого пользователем: "сегодня", "завтра", "+N" (дней от сегодня)
+/// или ГГГГ-ММ-ДД
+pub fn parse_due_date(value: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let relative = match value.trim() {
+        "сегодня" => Some(today),
+        "завтра" => today.checked_add_days(Days::new(1)),
+        other => other
+            .strip_prefix('+')
+            .and_then(|days| days.parse::<u64>().ok())
+            .and_then(|days| today.checked_add_days(Days::new(days))),
+    };
+    match relative {
+        Some(date) => Ok(date),
+        None => NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+            "❌ Неверный формат даты. Используйте ГГГГ-ММ-ДД, сегодня, завтра или +N".to_string()
+        }),
+    }
+}
+
+/// Порядок вывода списка задач.
+/// По умолчанию — по приоритету: срочные задачи должны быть видны первыми,
+/// порядок добавления выбирается явно (`TODO_SORT=insertion`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Sortirovka {
+    #[default]
+    Prioritet, // По убыванию приоритета, внутри — в порядке добавления
+    Vstavka,  // В порядке добавления
+    Srok,     // По сроку, задачи без срока в конце
+    Sozdanie, // По дате создания
+    Umnaya,   // Сначала невыполненные с ближайшим сроком
+}
+
+impl Sortirovka {
+    /// Разбор значения из `TODO_SORT` или из команды `список по <x>`
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "due" | "срок" | "сроку" => Some(Sortirovka::Srok),
+            "created" | "созданию" | "создание" => Some(Sortirovka::Sozdanie),
+            "smart" | "умно" | "умная" => Some(Sortirovka::Umnaya),
+            "insertion" | "добавлению" => Some(Sortirovka::Vstavka),
+            "priority" | "приоритету" | "приоритет" => {
+                Some(Sortirovka::Prioritet)
+            }
+            _ => None,
+        }
+    }
+
+    /// Сортировка представления без изменения исходного вектора
+    pub fn apply(&self, zadaniey: &mut [&Zadanie]) {
+        // Задачи без срока уходят в конец
+        let due = |t: &Zadanie| t.due_date.unwrap_or(NaiveDate::MAX);
+        match self {
+            Sortirovka::Prioritet => zadaniey.sort_by_key(|t| Reverse(t.priority)),
+            Sortirovka::Vstavka => {}
+            // Внутри одной даты выполненные идут после невыполненных
+            Sortirovka::Srok => zadaniey.sort_by_key(|t| (due(t), t.done, Reverse(t.priority))),
+            Sortirovka::Sozdanie => zadaniey.sort_by_key(|t| t.created_at),
+            Sortirovka::Umnaya => zadaniey.sort_by_key(|t| (t.done, due(t), Reverse(t.priority))),
+        }
+    }
+}
+
+/// Поиск подстроки без учёта регистра
+pub fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// Одна страница списка задач вместе со сводкой по всему списку
+#[derive(Debug)]
+pub struct StranitsaSpiska<'a> {
+    pub progress: f32,              // Процент выполненных среди всех задач
+    pub overdue: usize,             // Просроченных среди всех задач
+    pub zadaniey: Vec<&'a Zadanie>, // Задачи этой страницы после фильтра и сортировки
+    pub page: usize,                // Номер страницы, с 1
+    pub pages: usize,               // Всего страниц (не меньше одной)
+}
+
+/// Сводные показатели по всем задачам
+#[derive(Debug, PartialEq)]
+pub struct Statistika {
+    pub total: usize,              // Всего задач
+    pub completed: usize,          // Выполнено
+    pub overdue: usize,            // Просрочено
+    pub with_due: usize,           // Со сроком
+    pub average_days: Option<f64>, // Среднее время выполнения в днях, если есть данные
+}
+
+/// За сколько последних дней считается скорость выполнения для прогноза
+pub const FORECAST_DAYS: u64 = 14;
+
+/// Прогноз завершения невыполненных задач
+#[derive(Debug, PartialEq)]
+pub enum Prognoz {
+    VsyoVypolneno, // Невыполненных задач нет
+    MaloDannykh,   // За FORECAST_DAYS дней ничего не выполнено
+    Zavershenie {
+        per_day: f64,    // Выполняется задач в день
+        pending: usize,  // Осталось задач
+        days: u64,       // Примерно дней до завершения
+        date: NaiveDate, // Ожидаемая дата завершения
+    },
+}
+
+/// Основной контейнер для работы с задачами
+pub struct ToDolist {
+    pub zadaniey: Vec<Zadanie>,          // Динамический массив задач
+    next_id: usize,                      // Счётчик для генерации новых ID
+    pub clock: Box<dyn Clock>,           // Источник текущего времени
+    pub sort: Sortirovka,                // Порядок вывода по умолчанию
+    pub max_per_day: usize,              // Порог предупреждения о перегруженном дне
+    pub page_size: usize,                // Задач на одной странице списка
+    pub istochnik: &'static str,         // Источник, записываемый в новые задачи
+    pub ephemeral: bool,                 // Режим без сохранения: persist ничего не пишет
+    pub reminder_file: Option<PathBuf>,  // Файл напоминаний для внешних программ
+    history: Vec<(Vec<Zadanie>, usize)>, // Снимки задач и next_id для отмены
+}
+
+/// Сколько последних действий можно отменить
+const UNDO_DEPTH: usize = 10;
+
+impl ToDolist {
+    /// Создание нового пустого списка
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        ToDolist {
+            zadaniey: Vec::new(), // Пустой вектор
+            next_id: 1,           // Начинаем с ID = 1
+            clock,
+            sort: Sortirovka::default(),
+            max_per_day: 5,
+            page_size: 20,
+            istochnik: ISTOCHNIK_INTERAKTIV,
+            ephemeral: false,
+            reminder_file: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// Создание списка из загруженных задач
+    pub fn from_zadaniey(zadaniey: Vec<Zadanie>, clock: Box<dyn Clock>) -> Self {
+        // Восстанавливаем next_id как максимальный ID + 1
+        let next_id = zadaniey.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+        ToDolist {
+            zadaniey,
+            next_id,
+            clock,
+            sort: Sortirovka::default(),
+            max_per_day: 5,
+            page_size: 20,
+            istochnik: ISTOCHNIK_INTERAKTIV,
+            ephemeral: false,
+            reminder_file: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// Сегодняшняя дата по часам списка
+    pub fn today(&self) -> NaiveDate {
+        self.clock.now().date_naive()
+    }
+
+    /// Задачи, подходящие под фильтр
+    pub fn filtered(&self, filtr: &FiltrSpiska) -> Vec<&Zadanie> {
+        let today = self.today();
+        self.zadaniey
+            .iter()
+            .filter(|t| filtr.matches(t, today))
+            .collect()
+    }
+
+    /// Задачи в том виде, в каком их показывает список: после фильтра и сортировки
+    pub fn visible_tasks(&self, filtr: &FiltrSpiska, sort: Sortirovka) -> Vec<&Zadanie> {
+        let mut view = self.filtered(filtr);
+        sort.apply(&mut view);
+        view
+    }
+
+    /// Сколько других невыполненных задач (кроме `except`) уже назначено на дату,
+    /// если их больше порога max_per_day
+    pub fn overbooked(&self, date: NaiveDate, except: usize) -> Option<usize> {
+        let count = self
+            .zadaniey
+            .iter()
+            .filter(|t| t.id != except && t.is_due_on(date))
+            .count();
+        (count > self.max_per_day).then_some(count)
+    }
+
+    /// ID невыполненной задачи с тем же текстом (без учёта регистра и меток)
+    pub fn find_duplicate(&self, text: &str) -> Option<usize> {
+        let text = bare_text(text).to_lowercase();
+        self.zadaniey
+            .iter()
+            .find(|t| !t.done && t.text.trim().to_lowercase() == text)
+            .map(|t| t.id)
+    }
+
+    /// Отказ в добавлении дубликата; force разрешает его
+    pub fn check_duplicate(&self, text: &str, force: bool) -> Result<(), String> {
+        match self.find_duplicate(text) {
+            Some(id) if !force => Err(format!(
+                "❌ Такая задача уже есть (ID: {}). Добавьте --force, чтобы всё равно создать её",
+                id
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Добавление задачи без срока, возвращает ID новой задачи
+    pub fn add(&mut self, text: String, force: bool) -> Result<usize, String> {
+        check_not_empty(&text)?;
+        self.check_duplicate(&text, force)?;
+        let zadanie = Zadanie::new(self.next_id, text, self.clock.as_ref());
+        Ok(self.push_new(zadanie))
+    }
+
+    /// Общая часть добавления: метка приоритета, источник и новый ID
+    pub fn push_new(&mut self, mut zadanie: Zadanie) -> usize {
+        let (priority, text) = split_priority(&zadanie.text);
+        let (tags, text) = split_tags(&text);
+        let (recur, text) = split_recurrence(&text);
+        zadanie.priority = priority;
+        zadanie.tags = tags;
+        zadanie.text = text;
+        if let Some(rule) = recur {
+            zadanie.recur = Some(rule.to_string());
+        }
+        zadanie.istochnik = Some(self.istochnik.to_string());
+        self.zadaniey.push(zadanie);
+        self.next_id += 1; // Увеличиваем счётчик
+        self.next_id - 1
+    }
+
+    /// Добавление задачи со сроком выполнения, возвращает ID новой задачи.
+    /// С force принимаются срок раньше сегодняшнего (для записей задним числом) и дубликат
+    pub fn add_with_date(
+        &mut self,
+        text: String,
+        date_str: &str,
+        force: bool,
+    ) -> Result<usize, String> {
+        // Текст проверяем раньше даты; дата — ГГГГ-ММ-ДД или относительная
+        check_not_empty(&text)?;
+        let due = parse_due_date(date_str, self.today())?;
+        if due < self.today() && !force {
+            return Err(
+                "❌ Срок в прошлом (добавьте --force, чтобы всё равно создать задачу)".to_string(),
+            );
+        }
+        self.check_duplicate(&text, force)?;
+        let zadanie = Zadanie::with_due_date(self.next_id, text, Some(due), self.clock.as_ref());
+        Ok(self.push_new(zadanie))
+    }
+
+    /// Страница отфильтрованного списка; прогресс и просрочка считаются по всем задачам.
+    /// Номер страницы вне диапазона прижимается к краю
+    pub fn page(&self, filtr: &FiltrSpiska, sort: Sortirovka, page: usize) -> StranitsaSpiska<'_> {
+        let total = self.zadaniey.len();
+        let done = self.zadaniey.iter().filter(|t| t.done).count();
+        let progress = if total == 0 {
+            0.0
+        } else {
+            (done as f32 / total as f32) * 100.0
+        };
+        let today = self.today();
+        let overdue = self.zadaniey.iter().filter(|t| t.is_overdue(today)).count();
+
+        let view = self.visible_tasks(filtr, sort);
+        let pages = view.len().div_ceil(self.page_size).max(1);
+        let page = page.clamp(1, pages);
+        let start = (page - 1) * self.page_size;
+        StranitsaSpiska {
+            progress,
+            overdue,
+            zadaniey: view.into_iter().skip(start).take(self.page_size).collect(),
+            page,
+            pages,
+        }
+    }
+
+    /// Откладывание задачи: скрыть из списка до указанной даты
+    pub fn snooze(&mut self, id: usize, date_str: &str) -> Result<(), String> {
+        let until = parse_due_date(date_str, self.today())?;
+        if until <= self.today() {
+            return Err("❌ Отложить можно только до даты в будущем".to_string());
+        }
+        match self.zadaniey.iter_mut().find(|t| t.id == id) {
+            Some(zadanie) => {
+                zadanie.otlozheno_do = Some(until);
+                zadanie.raw_otlozheno_do = None;
+                Ok(())
+            }
+            None => Err(ToDoError::NeNaydena(id).to_string()),
+        }
+    }
+
+    /// Установка срока существующей задаче; None снимает срок
+    pub fn set_due_date(&mut self, id: usize, date: Option<&str>) -> Result<(), String> {
+        self.find(id)?;
+        let today = self.today();
+        let due = date.map(|d| parse_due_date(d, today)).transpose()?;
+        if let Some(zadanie) = self.zadaniey.iter_mut().find(|t| t.id == id) {
+            zadanie.due_date = due;
+            zadanie.raw_due_date = None; // Старое нераспознанное значение больше не нужно
+        }
+        Ok(())
+    }
+
+    /// Заметка к задаче; None удаляет её
+    pub fn set_note(&mut self, id: usize, note: Option<&str>) -> Result<(), String> {
+        match self.zadaniey.iter_mut().find(|t| t.id == id) {
+            Some(zadanie) => {
+                zadanie.note = note.map(str::to_string);
+                Ok(())
+            }
+            None => Err(ToDoError::NeNaydena(id).to_string()),
+        }
+    }
+
+    /// Прогноз разбора невыполненных задач по средней скорости за последние FORECAST_DAYS дней
+    pub fn forecast(&self) -> Prognoz {
+        let today = self.today();
+        let since = today - Days::new(FORECAST_DAYS - 1); // Окно включает сегодняшний день
+        let completed = self
+            .zadaniey
+            .iter()
+            .filter_map(|t| t.completed_at)
+            .filter(|at| at.date_naive() >= since && at.date_naive() <= today)
+            .count();
+        let pending = self.zadaniey.iter().filter(|t| !t.done).count();
+
+        if pending == 0 {
+            return Prognoz::VsyoVypolneno;
+        }
+        if completed == 0 {
+            return Prognoz::MaloDannykh;
+        }
+
+        let per_day = completed as f64 / FORECAST_DAYS as f64;
+        let days = (pending as f64 / per_day).ceil() as u64;
+        Prognoz::Zavershenie {
+            per_day,
+            pending,
+            days,
+            date: today + Days::new(days),
+        }
+    }
+
+    /// Сводная статистика по всем задачам
+    pub fn stats(&self) -> Statistika {
+        let today = self.today();
+
+        // Без распознанной даты создания и при выполнении раньше создания
+        // длительность неизвестна; такие задачи не учитываем
+        let durations: Vec<f64> = self
+            .zadaniey
+            .iter()
+            .filter(|t| t.done && t.raw_created_at.is_none())
+            .filter_map(|t| t.completed_at.map(|at| at - t.created_at))
+            .filter(|spent| *spent >= TimeDelta::zero())
+            .map(|spent| spent.num_seconds() as f64 / 86_400.0)
+            .collect();
+
+        Statistika {
+            total: self.zadaniey.len(),
+            completed: self.zadaniey.iter().filter(|t| t.done).count(),
+            overdue: self.zadaniey.iter().filter(|t| t.is_overdue(today)).count(),
+            with_due: self
+                .zadaniey
+                .iter()
+                .filter(|t| t.due_date.is_some())
+                .count(),
+            average_days: (!durations.is_empty())
+                .then(|| durations.iter().sum::<f64>() / durations.len() as f64),
+        }
+    }
+
+    /// Отметка задачи как выполненной по ID.
+    /// Для повторяющейся задачи добавляет следующее повторение и возвращает его ID;
+    /// пока созданное раньше повторение не удалено, новое не добавляется
+    pub fn complete(&mut self, id: usize) -> Result<Option<usize>, String> {
+        let today = self.today();
+        let ids: Vec<usize> = self.zadaniey.iter().map(|t| t.id).collect();
+        let zadanie = match self.zadaniey.iter_mut().find(|t| t.id == id) {
+            Some(zadanie) => zadanie,
+            None => return Err(ToDoError::NeNaydena(id).to_string()),
+        };
+        let was_done = zadanie.done;
+        zadanie.complete(self.clock.as_ref());
+        // Повторное выполнение, в том числе после "вернуть", не порождает новую копию
+        let has_copy = zadanie.next_copy.is_some_and(|copy| ids.contains(&copy));
+        if was_done || has_copy {
+            return Ok(None);
+        }
+        let Some(rule) = zadanie.recur.clone() else {
+            return Ok(None);
+        };
+
+        // Копия с тем же текстом и метками, срок сдвигается по правилу
+        let due = zadanie.next_occurrence(today);
+        let mut copy =
+            Zadanie::with_due_date(self.next_id, zadanie.text.clone(), due, self.clock.as_ref());
+        copy.recur = Some(rule);
+        copy.priority = zadanie.priority;
+        copy.tags = zadanie.tags.clone();
+        copy.note = zadanie.note.clone();
+        copy.istochnik = zadanie.istochnik.clone();
+        zadanie.next_copy = Some(copy.id);
+        self.zadaniey.push(copy);
+        self.next_id += 1;
+        Ok(Some(self.next_id - 1))
+    }
+
+    /// Возврат выполненной задачи в работу по ID.
+    /// Возвращает false, если задача и так не была выполнена
+    pub fn reopen(&mut self, id: usize) -> Result<bool, String> {
+        match self.zadaniey.iter_mut().find(|t| t.id == id) {
+            Some(zadanie) if !zadanie.done => Ok(false),
+            Some(zadanie) => {
+                zadanie.reopen();
+                Ok(true)
+            }
+            None => Err(ToDoError::NeNaydena(id).to_string()),
+        }
+    }
+
+    /// Изменение текста задачи по ID (статус и даты не трогаются).
+    /// Текст разбирается как при добавлении: приоритет и правило повтора из него
+    /// заменяют прежние, метки добавляются к прежним
+    pub fn edit(&mut self, id: usize, text: String) -> Result<(), String> {
+        self.find(id)?;
+        check_not_empty(&text)?;
+        let text = text.trim();
+        let (priority, rest) = split_priority(text);
+        let explicit_priority = rest != text; // Без метки приоритет остаётся прежним
+        let (tags, rest) = split_tags(&rest);
+        let (recur, rest) = split_recurrence(&rest);
+        if let Some(zadanie) = self.zadaniey.iter_mut().find(|t| t.id == id) {
+            if explicit_priority {
+                zadanie.priority = priority;
+            }
+            for tag in tags {
+                if !zadanie.tags.contains(&tag) {
+                    zadanie.tags.push(tag);
+                }
+            }
+            if let Some(rule) = recur {
+                zadanie.recur = Some(rule.to_string());
+            }
+            zadanie.text = rest;
+        }
+        Ok(())
+    }
+
+    /// Повторяющиеся задачи вместе с их следующим сроком.
+    /// Выполненные не показываются: их продолжает невыполненная копия
+    pub fn recurring(&self) -> Vec<(&Zadanie, Option<NaiveDate>)> {
+        let today = self.today();
+        self.zadaniey
+            .iter()
+            .filter(|t| !t.done && t.recur.is_some())
+            .map(|t| (t, t.next_occurrence(today)))
+            .collect()
+    }
+
+    /// Поиск по всем текстовым полям; для каждого совпадения — задача и название поля
+    pub fn search_all(&self, query: &str) -> Vec<(&Zadanie, &str)> {
+        let mut found = Vec::new();
+        for zadanie in &self.zadaniey {
+            for (label, value) in zadanie.text_fields() {
+                if contains_ignore_case(value, query) {
+                    found.push((zadanie, label));
+                }
+            }
+        }
+        found
+    }
+
+    /// Задачи, текст которых содержит подстроку (без учёта регистра)
+    pub fn search_text(&self, query: &str) -> Vec<&Zadanie> {
+        self.zadaniey
+            .iter()
+            .filter(|t| contains_ignore_case(&t.text, query))
+            .collect()
+    }
+
+    /// Поиск задачи по ID
+    pub fn find(&self, id: usize) -> Result<&Zadanie, String> {
+        self.zadaniey
+            .iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| ToDoError::NeNaydena(id).to_string())
+    }
+
+    /// Сдвиг срока задачи на `days` дней (отрицательное значение — раньше).
+    /// Задача без срока получает срок от сегодня, если `from_today`, иначе пропускается.
+    /// Возвращает, был ли срок изменён
+    pub fn postpone(&mut self, id: usize, days: i64, from_today: bool) -> Result<bool, String> {
+        let today = self.today();
+        let zadanie = self.find(id)?;
+        let base = match zadanie.due_date {
+            Some(date) => date,
+            None if from_today => today,
+            None => return Ok(false),
+        };
+        let new_date = base
+            .checked_add_signed(TimeDelta::days(days))
+            .ok_or_else(|| "❌ Слишком большой сдвиг срока".to_string())?;
+
+        if let Some(zadanie) = self.zadaniey.iter_mut().find(|t| t.id == id) {
+            zadanie.due_date = Some(new_date);
+            zadanie.raw_due_date = None;
+        }
+        Ok(true)
+    }
+
+    /// Перенос невыполненных задач со вчерашним сроком на сегодня, возвращает их ID
+    pub fn carry_over(&mut self) -> Vec<usize> {
+        let today = self.today();
+        let yesterday = today - Days::new(1);
+        let mut moved = Vec::new();
+        for zadanie in self.zadaniey.iter_mut() {
+            if zadanie.is_due_on(yesterday) {
+                zadanie.due_date = Some(today);
+                moved.push(zadanie.id);
+            }
+        }
+        moved
+    }
+
+    /// Проверка целостности отметок времени: задачи, выполненные раньше создания
+    pub fn check_timestamps(&self) -> Vec<&Zadanie> {
+        self.zadaniey
+            .iter()
+            .filter(|t| {
+                t.completed_at
+                    .is_some_and(|completed| completed < t.created_at)
+            })
+            .collect()
+    }
+
+    /// Задачи, даты которых не удалось распознать при миграции
+    pub fn unparsed_dates(&self) -> Vec<&Zadanie> {
+        self.zadaniey
+            .iter()
+            .filter(|t| !t.raw_dates().is_empty())
+            .collect()
+    }
+
+    /// Исправление: время выполнения приравнивается ко времени создания
+    pub fn fix_completed_before_created(&mut self, ids: &[usize]) -> usize {
+        let mut count = 0;
+        for zadanie in self.zadaniey.iter_mut() {
+            if ids.contains(&zadanie.id) && zadanie.completed_at.is_some() {
+                zadanie.completed_at = Some(zadanie.created_at);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Массовое выполнение задач по списку ID, возвращает число выполненных
+    pub fn complete_many(&mut self, ids: &[usize]) -> usize {
+        let pending: Vec<usize> = self
+            .zadaniey
+            .iter()
+            .filter(|t| !t.done && ids.contains(&t.id))
+            .map(|t| t.id)
+            .collect();
+        for &id in &pending {
+            let _ = self.complete(id); // ID только что найдены в списке
+        }
+        pending.len()
+    }
+
+    /// Удаление задачи по ID
+    /// Возвращает удалённую задачу, чтобы её можно было вернуть
+    pub fn remove(&mut self, id: usize) -> Result<Zadanie, ToDoError> {
+        let index = self.zadaniey.iter().position(|t| t.id == id);
+        match index {
+            Some(i) => Ok(self.zadaniey.remove(i)),
+            None => Err(ToDoError::NeNaydena(id)),
+        }
+    }
+
+    /// Снимок состояния перед командой; повторный снимок без изменений не сохраняется
+    pub fn remember(&mut self) {
+        if let Some((zadaniey, next_id)) = self.history.last() {
+            if *zadaniey == self.zadaniey && *next_id == self.next_id {
+                return;
+            }
+        }
+        self.history.push((self.zadaniey.clone(), self.next_id));
+        if self.history.len() > UNDO_DEPTH {
+            self.history.remove(0); // Самый старый снимок больше не нужен
+        }
+    }
+
+    /// Возврат к последнему снимку, отличающемуся от текущего состояния.
+    /// Возвращает false, если отменять нечего
+    pub fn undo(&mut self) -> bool {
+        while let Some((zadaniey, next_id)) = self.history.pop() {
+            if zadaniey != self.zadaniey || next_id != self.next_id {
+                self.zadaniey = zadaniey;
+                self.next_id = next_id;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Замена всех задач восстановленными; next_id не уменьшается,
+    /// чтобы ID уже выданных задач не достались новым
+    pub fn restore(&mut self, zadaniey: Vec<Zadanie>) {
+        let max_id = zadaniey.iter().map(|t| t.id).max().unwrap_or(0);
+        self.next_id = self.next_id.max(max_id + 1);
+        self.zadaniey = zadaniey;
+    }
+
+    /// Удаление всех выполненных задач, возвращает число удалённых.
+    /// next_id не трогаем, чтобы ID новых задач не совпали со старыми
+    pub fn clear_completed(&mut self) -> usize {
+        let before = self.zadaniey.len();
+        self.zadaniey.retain(|t| !t.done);
+        before - self.zadaniey.len()
+    }
+}
+
+/// Путь рядом с файлом задач: "zadaniey.json" + ".autosave"
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Страховочная копия на случай сбоя
+pub fn autosave_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".autosave")
+}
+
+/// Сохранение задач в JSON файл с резервной копией прежнего содержимого.
+/// Возвращает предупреждения: без резервной копии сохранение всё равно выполняется
+pub fn save_to_file(path: &Path, zadaniey: &[Zadanie]) -> io::Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    if let Err(e) = backup_before_save(path) {
+        warnings.push(format!("⚠️ Не удалось сделать резервную копию: {}", e));
+    }
+    write_to(path, zadaniey)?;
+    Ok(warnings)
+}
+
+/// Сколько последних резервных копий хранить
+const BACKUP_LIMIT: usize = 10;
+
+/// Каталог резервных копий рядом с файлом задач
+fn backups_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join("backups")
+}
+
+/// Имя файла задач без расширения: "zadaniey"
+fn data_stem(path: &Path) -> &str {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("zadaniey")
+}
+
+/// Копия текущего файла в backups/zadaniey-ГГГГММДД-ЧЧММСС.json; старые копии сверх лимита удаляются
+fn backup_before_save(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(()); // Копировать пока нечего
+    }
+    let dir = backups_dir(path);
+    std::fs::create_dir_all(&dir)?;
+    let name = format!(
+        "{}-{}.json",
+        data_stem(path),
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+    std::fs::copy(path, dir.join(name))?;
+
+    let backups = list_backups(path)?;
+    if backups.len() > BACKUP_LIMIT {
+        for old in &backups[..backups.len() - BACKUP_LIMIT] {
+            std::fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+/// Резервные копии файла задач, от старых к новым
+pub fn list_backups(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = backups_dir(path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}-", data_stem(path));
+    // Подходят только имена вида <имя>-ГГГГММДД-ЧЧММСС.json
+    let is_backup = |name: &str| {
+        name.strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .is_some_and(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S").is_ok())
+    };
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_backup)
+        })
+        .collect();
+    backups.sort(); // Метка времени в имени сортируется как дата
+    Ok(backups)
+}
+
+/// Запись задач в JSON по указанному пути.
+/// Данные пишутся во временный файл и атомарно подменяют старый через rename,
+/// поэтому прерванная запись не портит существующий файл
+pub fn write_to(path: &Path, zadaniey: &[Zadanie]) -> io::Result<()> {
+    let fayl = FaylZadach {
+        version: SCHEMA_VERSION,
+        tasks: zadaniey,
+    };
+    let json = serde_json::to_string(&fayl)?; // Сериализация в JSON
+    let tmp_path = with_suffix(path, ".tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true) // Создать если не существует
+        .truncate(true) // Очистить если остался от прошлой попытки
+        .open(&tmp_path)?; // Открыть временный файл
+    file.write_all(json.as_bytes())?; // Записать данные
+    file.sync_all()?; // Дождаться записи на диск до подмены
+    drop(file);
+    std::fs::rename(&tmp_path, path) // Атомарная замена основного файла
+}
+
+/// Блокировка файла задач на время работы: "<файл>.lock" с PID владельца.
+/// Снимается при выходе и при панике (через Drop)
+pub struct Blokirovka {
+    path: PathBuf,
+}
+
+impl Blokirovka {
+    /// Захват блокировки; force удаляет оставшуюся после аварийного завершения
+    pub fn acquire(data_path: &Path, force: bool) -> Result<Self, String> {
+        let path = with_suffix(data_path, ".lock");
+        if force {
+            let _ = std::fs::remove_file(&path);
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", std::process::id()); // PID для диагностики
+                Ok(Blokirovka { path })
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let owner = std::fs::read_to_string(&path).unwrap_or_default();
+                let owner = owner.trim();
+                // Где есть /proc, можно проверить, жив ли владелец
+                let stale = Path::new("/proc").is_dir()
+                    && !owner.is_empty()
+                    && !Path::new("/proc").join(owner).exists();
+                if stale {
+                    Err(format!(
+                        "❌ {} остался от завершившегося процесса (PID {}). Запустите с --force-unlock, чтобы снять блокировку",
+                        path.display(),
+                        owner
+                    ))
+                } else {
+                    Err(format!(
+                        "❌ Файл задач уже открыт другой копией программы (PID {}). Если это не так, запустите с --force-unlock",
+                        if owner.is_empty() { "?" } else { owner }
+                    ))
+                }
+            }
+            Err(e) => Err(format!("❌ Не удалось создать {}: {}", path.display(), e)),
+        }
+    }
+}
+
+impl Drop for Blokirovka {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Повреждённый файл переименовывается в "<файл>.corrupt-ГГГГММДД-ЧЧММСС",
+/// возвращается новый путь
+pub fn salvage_corrupt(path: &Path) -> io::Result<PathBuf> {
+    let suffix = format!(".corrupt-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let mut moved = with_suffix(path, &suffix);
+    let mut attempt = 1;
+    while moved.exists() {
+        // Прошлая спасённая копия в ту же секунду не должна затереться
+        moved = with_suffix(path, &format!("{}-{}", suffix, attempt));
+        attempt += 1;
+    }
+    std::fs::rename(path, &moved)?;
+    Ok(moved)
+}
+
+/// Ошибка разбора файла задач. Любая такая ошибка означает повреждение, в том числе
+/// обрыв посреди записи, который serde_json относит к UnexpectedEof
+fn corrupt(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Чтение задач из JSON по указанному пути.
+/// Повреждённый файл даёт ошибку `ErrorKind::InvalidData`,
+/// формат новее поддерживаемого — `ErrorKind::Unsupported`
+pub fn load_from_file(path: &Path) -> io::Result<Vec<Zadanie>> {
+    if !path.exists() {
+        return Ok(Vec::new()); // Вернуть пустой вектор если файла нет
+    }
+
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?; // Чтение всего файла
+    let value: serde_json::Value = serde_json::from_str(&contents).map_err(corrupt)?; // Парсинг JSON
+    if value.is_array() {
+        return migrate_v0(value); // Массив без обёртки — формат версии 0
+    }
+
+    let fayl: ZagruzhennyFayl = serde_json::from_value(value).map_err(corrupt)?;
+    match fayl.version {
+        SCHEMA_VERSION => serde_json::from_value(fayl.tasks).map_err(corrupt),
+        0 => migrate_v0(fayl.tasks),
+        // Файл от более новой версии программы — не повреждение, трогать его нельзя
+        version => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "формат версии {} новее поддерживаемого ({}), обновите программу",
+                version, SCHEMA_VERSION
+            ),
+        )),
+    }
+}
+
+/// Текущая версия формата файла задач
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Файл задач на диске: { "version": N, "tasks": [...] }
+#[derive(Serialize)]
+struct FaylZadach<'a> {
+    version: u32,
+    tasks: &'a [Zadanie],
+}
+
+/// Обёртка при чтении: задачи разбираются после проверки версии
+#[derive(Deserialize)]
+struct ZagruzhennyFayl {
+    version: u32,
+    tasks: serde_json::Value,
+}
+
+/// Версия 0 — массив задач без обёртки; в самых старых файлах даты хранились строками
+fn migrate_v0(tasks: serde_json::Value) -> io::Result<Vec<Zadanie>> {
+    match serde_json::from_value(tasks.clone()) {
+        Ok(zadaniey) => Ok(zadaniey),
+        Err(err) => {
+            // Даты строками переводим в типизированные
+            let staryye: Vec<StarayaZadanie> =
+                serde_json::from_value(tasks).map_err(|_| corrupt(err))?;
+            Ok(staryye.into_iter().map(StarayaZadanie::migrate).collect())
+        }
+    }
+}
+
+/// Экранирование текста по правилам iCalendar (RFC 5545)
+fn escape_ics(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Перенос длинной строки iCalendar: не более 75 байт, продолжение с пробела
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Экспорт задач со сроком в файл iCalendar (по одному VTODO на задачу)
+pub fn export_ics(zadaniey: &[Zadanie], path: &str) -> io::Result<()> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo-cli//RU".to_string(),
+    ];
+    for zadanie in zadaniey {
+        // Задачи без срока пропускаются
+        let Some(due) = zadanie.due_date else {
+            continue;
+        };
+        // DTSTAMP обязателен: берём время создания
+        let stamp = zadanie.created_at.naive_utc();
+        let status = if zadanie.done {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:zadanie-{}@todo-cli", zadanie.id));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        lines.push(format!("SUMMARY:{}", escape_ics(&zadanie.text)));
+        lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+        lines.push(format!("STATUS:{}", status));
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let contents: String = lines.iter().map(|line| fold_ics_line(line)).collect();
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Экранирование поля CSV по RFC 4180: кавычки, если есть запятая, кавычка или перевод строки
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Экспорт всех задач в CSV для таблиц (пустая ячейка — значения нет)
+pub fn export_csv(zadaniey: &[Zadanie], path: &Path) -> io::Result<()> {
+    let mut contents = String::from("id,text,done,created_at,completed_at,due_date\r\n");
+    for zadanie in zadaniey {
+        let row = [
+            zadanie.id.to_string(),
+            escape_csv(&zadanie.text),
+            zadanie.done.to_string(),
+            zadanie.created_at.to_rfc3339(),
+            zadanie
+                .completed_at
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_default(),
+            zadanie
+                .due_date
+                .map(|due| due.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+        ];
+        contents.push_str(&row.join(","));
+        contents.push_str("\r\n");
+    }
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Сохранение после изменения: автосохранение, основной файл и файл напоминаний.
+/// Ошибка — только если не записан основной файл; остальные сбои возвращаются
+/// предупреждениями
+pub fn persist(todo: &ToDolist, path: &Path) -> io::Result<Vec<String>> {
+    if todo.ephemeral {
+        return Ok(Vec::new());
+    }
+    let mut warnings = Vec::new();
+    // Страховочная копия не должна мешать записи основного файла
+    if let Err(e) = write_to(&autosave_path(path), &todo.zadaniey) {
+        warnings.push(format!("⚠️ Не удалось записать автосохранение: {}", e));
+    }
+    warnings.extend(save_to_file(path, &todo.zadaniey)?);
+    if let Err(e) = write_reminders(todo) {
+        warnings.push(format!("⚠️ Не удалось записать файл напоминаний: {}", e));
+    }
+    Ok(warnings)
+}
+
+/// Файл напоминаний для внешних программ: просроченные задачи и задачи на сегодня.
+/// Пишется только если задан reminder_file
+pub fn write_reminders(todo: &ToDolist) -> io::Result<()> {
+    let Some(path) = &todo.reminder_file else {
+        return Ok(());
+    };
+    if todo.ephemeral {
+        return Ok(());
+    }
+
+    let today = todo.today();
+    let mut contents = String::new();
+    for zadanie in &todo.zadaniey {
+        if zadanie.is_overdue(today) {
+            contents.push_str(&format!("{} (просрочено)\n", zadanie.format_line()));
+        } else if zadanie.is_due_on(today) {
+            contents.push_str(&format!("{} (сегодня)\n", zadanie.format_line()));
+        }
+    }
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Автосохранение новее основного файла — значит, прошлый сеанс упал до записи
+pub fn autosave_is_newer(path: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(&autosave_path(path)), modified(path)) {
+        (Some(autosave), Some(data)) => autosave > data,
+        (Some(_), None) => true, // Основного файла нет вовсе
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Часы, остановленные на 24.06.2025 12:00
+    fn clock() -> FakeClock {
+        FakeClock {
+            now: Local.with_ymd_and_hms(2025, 6, 24, 12, 0, 0).unwrap(),
+        }
+    }
+
+    /// Пустой временный каталог для одного теста
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn interrupted_write_keeps_existing_file() {
+        let dir = temp_dir("write");
+        let path = dir.join("zadaniey.json");
+        let old = vec![Zadanie::new(1, "старая".to_string(), &clock())];
+        write_to(&path, &old).unwrap();
+
+        // Прерванная запись оставляет недописанный временный файл рядом
+        std::fs::write(with_suffix(&path, ".tmp"), b"[{\"id\":1,\"te").unwrap();
+        assert_eq!(load_from_file(&path).unwrap(), old);
+
+        let new = vec![Zadanie::new(2, "новая".to_string(), &clock())];
+        write_to(&path, &new).unwrap();
+        assert_eq!(load_from_file(&path).unwrap(), new);
+        assert!(!with_suffix(&path, ".tmp").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn duplicate_of_pending_task_needs_force() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        let id = todo.add("Купить молоко".to_string(), false).unwrap();
+
+        // Регистр и метки не делают задачу новой
+        let err = todo
+            .add("купить МОЛОКО #дом".to_string(), false)
+            .unwrap_err();
+        assert!(err.contains(&format!("ID: {}", id)));
+        assert_eq!(todo.zadaniey.len(), 1);
+
+        // С force дубликат создаётся
+        todo.add("Купить молоко".to_string(), true).unwrap();
+        assert_eq!(todo.zadaniey.len(), 2);
+    }
+
+    #[test]
+    fn completed_task_is_not_a_duplicate() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        let id = todo.add("Купить молоко".to_string(), false).unwrap();
+        todo.complete(id).unwrap();
+
+        assert_eq!(todo.find_duplicate("Купить молоко"), None);
+        todo.add("Купить молоко".to_string(), false).unwrap();
+        assert_eq!(todo.zadaniey.len(), 2);
+    }
+
+    #[test]
+    fn empty_text_creates_no_task() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        for text in ["", "   ", "#дом #работа", "!высокий  "] {
+            assert!(todo.add(text.to_string(), false).is_err(), "{:?}", text);
+            assert!(todo
+                .add_with_date(text.to_string(), "завтра", false)
+                .is_err());
+        }
+        assert!(todo.zadaniey.is_empty());
+    }
+
+    #[test]
+    fn legacy_array_is_saved_in_envelope() {
+        let dir = temp_dir("legacy");
+        let path = dir.join("zadaniey.json");
+        std::fs::write(
+            &path,
+            r#"[{"id":1,"text":"старая","done":false,"created_at":"2025-06-24 16:20:35.1 +03:00","completed_at":null,"due_date":"2025-07-01"}]"#,
+        )
+        .unwrap();
+
+        let zadaniey = load_from_file(&path).unwrap();
+        assert_eq!(zadaniey.len(), 1);
+        assert_eq!(zadaniey[0].due_date, NaiveDate::from_ymd_opt(2025, 7, 1));
+
+        write_to(&path, &zadaniey).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], SCHEMA_VERSION);
+        assert!(saved["tasks"].is_array());
+        assert_eq!(load_from_file(&path).unwrap(), zadaniey);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn newer_version_is_unsupported() {
+        let dir = temp_dir("newer");
+        let path = dir.join("zadaniey.json");
+        std::fs::write(&path, r#"{"version":99,"tasks":[]}"#).unwrap();
+        let err = load_from_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn add_splits_priority_tags_and_recurrence() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        let id = todo
+            .add(
+                "!высокий полить цветы #дом каждую неделю".to_string(),
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(id, 1);
+        assert_eq!(todo.zadaniey.len(), 1);
+        let zadanie = &todo.zadaniey[0];
+        assert_eq!(zadanie.text, "полить цветы");
+        assert_eq!(zadanie.priority, Prioritet::Vysokiy);
+        assert_eq!(zadanie.tags, vec!["дом".to_string()]);
+        assert_eq!(zadanie.recur.as_deref(), Some("weekly"));
+        assert_eq!(zadanie.istochnik.as_deref(), Some(ISTOCHNIK_INTERAKTIV));
+        assert_eq!(zadanie.created_at, clock().now);
+    }
+
+    #[test]
+    fn page_and_stats_count_all_tasks() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        todo.page_size = 2;
+        for text in ["первая", "вторая", "третья"] {
+            todo.add(text.to_string(), false).unwrap();
+        }
+        todo.add_with_date("просроченная".to_string(), "2025-06-01", true)
+            .unwrap();
+        todo.complete(1).unwrap();
+
+        let stranitsa = todo.page(&FiltrSpiska::Aktivnye, Sortirovka::Vstavka, 9);
+        assert_eq!((stranitsa.page, stranitsa.pages), (2, 2));
+        assert_eq!(stranitsa.zadaniey.len(), 1);
+        assert_eq!(stranitsa.progress, 25.0);
+        assert_eq!(stranitsa.overdue, 1);
+
+        let stats = todo.stats();
+        assert_eq!((stats.total, stats.completed, stats.overdue), (4, 1, 1));
+        assert_eq!(stats.with_due, 1);
+        assert_eq!(stats.average_days, Some(0.0));
+    }
+
+    #[test]
+    fn unreadable_file_is_corrupt_and_salvaged() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("zadaniey.json");
+        // Мусор, обрыв посреди записи, пустой файл и чужая структура
+        let samples: [&[u8]; 4] = [
+            b"\xff\xfe\x00garbage",
+            br#"{"version":1,"tasks":[{"id":1"#,
+            b"",
+            br#"{"version":1,"tasks":{"id":1}}"#,
+        ];
+        for contents in samples {
+            std::fs::write(&path, contents).unwrap();
+            let err = load_from_file(&path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", contents);
+
+            let moved = salvage_corrupt(&path).unwrap();
+            assert!(!path.exists());
+            assert_eq!(std::fs::read(&moved).unwrap(), contents);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reopened_recurring_task_spawns_one_copy() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        let id = todo
+            .add_with_date("полить цветы каждую неделю".to_string(), "завтра", false)
+            .unwrap();
+
+        let copy = todo.complete(id).unwrap();
+        assert!(copy.is_some());
+        assert!(todo.reopen(id).unwrap());
+        assert_eq!(todo.complete(id).unwrap(), None);
+        assert_eq!(todo.zadaniey.len(), 2);
+
+        // Удалённое повторение можно получить заново
+        todo.remove(copy.unwrap()).unwrap();
+        todo.reopen(id).unwrap();
+        assert!(todo.complete(id).unwrap().is_some());
+        assert_eq!(todo.zadaniey.len(), 2);
+        assert_eq!(todo.recurring().len(), 1); // Только невыполненная копия
+    }
+
+    #[test]
+    fn edit_parses_text_like_add() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        let id = todo
+            .add("!высокий позвонить #работа".to_string(), false)
+            .unwrap();
+
+        todo.edit(id, "  позвонить   маме  #дом".to_string())
+            .unwrap();
+        let zadanie = todo.find(id).unwrap();
+        assert_eq!(zadanie.text, "позвонить маме");
+        assert_eq!(zadanie.tags, vec!["работа".to_string(), "дом".to_string()]);
+        assert_eq!(zadanie.priority, Prioritet::Vysokiy);
+
+        // Одни метки — это пустой текст
+        assert!(todo.edit(id, "#дом".to_string()).is_err());
+        assert_eq!(todo.find(id).unwrap().text, "позвонить маме");
+    }
+
+    #[test]
+    fn overbooked_counts_other_tasks_over_limit() {
+        let mut todo = ToDolist::new(Box::new(clock()));
+        todo.max_per_day = 2;
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).unwrap();
+        let ids: Vec<usize> = (1..=3)
+            .map(|n| {
+                todo.add_with_date(format!("задача {}", n), "завтра", false)
+                    .unwrap()
+            })
+            .collect();
+
+        // Третьей задаче достались две другие — это ещё не больше порога
+        assert_eq!(todo.overbooked(day, ids[2]), None);
+        let fourth = todo
+            .add_with_date("четвёртая".to_string(), "завтра", false)
+            .unwrap();
+        assert_eq!(todo.overbooked(day, fourth), Some(3));
+    }
+}
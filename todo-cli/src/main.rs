@@ -1,270 +1,1343 @@
-use chrono::{Local, NaiveDate}; // Работа с датами/временем
+use chrono::{DateTime, Local, NaiveDate}; // Работа с датами/временем
 use serde::{Deserialize, Serialize}; // Сериализация/десериализация
-use std::fs::{File, OpenOptions}; // Работа с файлами
-use std::io::{self, Read, Write}; // Ввод/вывод
-use std::path::Path; // Работа с путями
-
-/// Структура задачи с автоматической сериализацией
-#[derive(Debug, Deserialize, Serialize)]
-struct Zadanie {
-    id: usize,                    // Уникальный числовой идентификатор
-    text: String,                 // Текст задачи
-    done: bool,                   // Статус выполнения (true/false)
-    created_at: String,           // Дата создания в строковом формате
-    completed_at: Option<String>, // Дата выполнения (None если не выполнена)
-    due_date: Option<String>,     // Срок выполнения (опционально)
-}
-
-impl Zadanie {
-    /// Создание новой задачи без срока выполнения
-    fn new(id: usize, text: String) -> Self {
-        Zadanie {
-            id,
-            text,
-            done: false,                          // По умолчанию не выполнена
-            created_at: Local::now().to_string(), // Текущая дата/время
-            completed_at: None,                   // Пока нет даты выполнения
-            due_date: None,                       // Срок не установлен
-        }
+use std::io::{self, IsTerminal, Write}; // Ввод/вывод
+use std::path::{Path, PathBuf}; // Работа с путями
+use std::process::{Command, Stdio}; // Запуск внешних утилит
+use std::sync::atomic::{AtomicBool, Ordering}; // Флаги режима работы
+use todo_cli::*; // Задачи, список и хранение
+
+/// Цвета ANSI в stdout и stderr (решается при запуске)
+static COLOR_OUT: AtomicBool = AtomicBool::new(false);
+static COLOR_ERR: AtomicBool = AtomicBool::new(false);
+
+fn color_out() -> bool {
+    COLOR_OUT.load(Ordering::Relaxed)
+}
+
+/// Цвета выключаются флагом --no-color и переменной NO_COLOR;
+/// поток, который не выводится в терминал, не раскрашивается
+fn init_colors() {
+    let allowed = !std::env::args().any(|arg| arg == "--no-color")
+        && std::env::var("NO_COLOR").map_or(true, |v| v.is_empty());
+    COLOR_OUT.store(allowed && io::stdout().is_terminal(), Ordering::Relaxed);
+    COLOR_ERR.store(allowed && io::stderr().is_terminal(), Ordering::Relaxed);
+}
+
+/// Вывод ошибки в stderr (красным, если можно)
+fn print_error(message: impl std::fmt::Display) {
+    let enabled = COLOR_ERR.load(Ordering::Relaxed);
+    eprintln!("{}", paint(&message.to_string(), RED, enabled));
+}
+
+/// Выбор часов: `TODO_NOW` (RFC 3339) фиксирует время, иначе системные
+fn clock_from_env() -> Box<dyn Clock> {
+    match std::env::var("TODO_NOW") {
+        Ok(value) => match DateTime::parse_from_rfc3339(&value) {
+            Ok(now) => Box::new(FakeClock {
+                now: now.with_timezone(&Local),
+            }),
+            Err(_) => {
+                eprintln!("⚠️ Неверное значение TODO_NOW, используется системное время");
+                Box::new(SystemClock)
+            }
+        },
+        Err(_) => Box::new(SystemClock),
     }
+}
 
-    /// Создание задачи с возможным сроком выполнения
-    fn with_due_date(id: usize, text: String, due_date: Option<String>) -> Self {
-        Zadanie {
-            id,
-            text,
-            done: false,
-            created_at: Local::now().to_string(),
-            completed_at: None,
-            due_date, // Устанавливаем переданный срок
-        }
+/// Порядок списка из `TODO_SORT`; без значения или при ошибке — Sortirovka::default()
+fn sort_from_env() -> Sortirovka {
+    match std::env::var("TODO_SORT") {
+        Ok(value) => Sortirovka::parse(&value).unwrap_or_else(|| {
+            eprintln!(
+                "⚠️ Неверное значение TODO_SORT (допустимо: due, priority, created, smart, insertion), используется сортировка по приоритету"
+            );
+            Sortirovka::default()
+        }),
+        Err(_) => Sortirovka::default(),
     }
+}
+
+/// Задачи без срока при массовом сдвиге: `TODO_SHIFT_UNDATED=today` ставит срок от сегодня
+fn shift_undated_from_today() -> bool {
+    std::env::var("TODO_SHIFT_UNDATED").is_ok_and(|v| v == "today")
+}
 
-    /// Отметка задачи как выполненной
-    fn complete(&mut self) {
-        self.done = true;
-        self.completed_at = Some(Local::now().to_string()); // Фиксируем время выполнения
+/// Порог задач на один день из `TODO_MAX_PER_DAY` (по умолчанию 5)
+fn max_per_day_from_env() -> usize {
+    match std::env::var("TODO_MAX_PER_DAY") {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("⚠️ Неверное значение TODO_MAX_PER_DAY, используется 5");
+            5
+        }),
+        Err(_) => 5,
     }
 }
 
-/// Основной контейнер для работы с задачами
-struct ToDolist {
-    zadaniey: Vec<Zadanie>, // Динамический массив задач
-    next_id: usize,         // Счётчик для генерации новых ID
+/// Задач на странице списка: TODO_PAGE_SIZE, по умолчанию 20
+fn page_size_from_env() -> usize {
+    match std::env::var("TODO_PAGE_SIZE") {
+        Ok(value) => match value.parse() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                eprintln!("⚠️ Неверное значение TODO_PAGE_SIZE, используется 20");
+                20
+            }
+        },
+        Err(_) => 20,
+    }
+}
+
+/// Файл задач: флаг `--file <путь>`, затем `TODO_FILE`, иначе `zadaniey.json`
+fn data_path_from_args() -> Result<PathBuf, String> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--file") {
+        return match args.get(index + 1) {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Err("❌ После --file нужно указать путь к файлу".to_string()),
+        };
+    }
+    match std::env::var("TODO_FILE") {
+        Ok(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Ok(PathBuf::from(DATA_FILE)),
+    }
 }
 
-impl ToDolist {
-    /// Создание нового пустого списка
-    fn new() -> Self {
-        ToDolist {
-            zadaniey: Vec::new(), // Пустой вектор
-            next_id: 1,           // Начинаем с ID = 1
+/// Сохранение после изменения с понятным сообщением об ошибке вместо паники;
+/// список в памяти при этом не теряется
+fn persist_or_warn(todo: &ToDolist, path: &Path) -> bool {
+    match persist(todo, path) {
+        Ok(warnings) => {
+            print_warnings(&warnings);
+            true
+        }
+        Err(e) => {
+            print_error(format!(
+                "❌ Не удалось сохранить задачи: {}. Изменения остаются в памяти.",
+                e
+            ));
+            false
         }
     }
+}
 
-    /// Добавление задачи без срока
-    fn add(&mut self, text: String) {
-        let zadanie = Zadanie::new(self.next_id, text);
-        self.zadaniey.push(zadanie);
-        self.next_id += 1; // Увеличиваем счётчик
-        println!("✅ Задача добавлена (ID: {})", self.next_id - 1);
+/// Предупреждения сохранения в stderr
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("{}", warning);
     }
+}
 
-    /// Добавление задачи со сроком выполнения
-    fn add_with_date(&mut self, text: String, date_str: &str) -> Result<(), String> {
-        // Парсим дату в формате ГГГГ-ММ-ДД
-        match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-            Ok(_) => {
-                // Если дата валидна
-                let zadanie =
-                    Zadanie::with_due_date(self.next_id, text, Some(date_str.to_string()));
-                self.zadaniey.push(zadanie);
-                self.next_id += 1;
-                println!("✅ Задача с сроком добавлена (ID: {})", self.next_id - 1);
-                Ok(())
-            }
-            Err(_) => Err("❌ Неверный формат даты. Используйте ГГГГ-ММ-ДД".to_string()),
-        }
+/// Предупреждение, если на день задачи уже назначено больше TODO_MAX_PER_DAY других невыполненных
+fn warn_if_overbooked(todo: &ToDolist, id: usize) {
+    let Some(due) = todo.find(id).ok().and_then(|t| t.due_date) else {
+        return;
+    };
+    if let Some(count) = todo.overbooked(due, id) {
+        eprintln!("⚠️ На {} уже {} задач", due, count);
     }
+}
 
-    /// Вывод списка задач с прогрессом
-    fn list(&self) {
-        if self.zadaniey.is_empty() {
-            println!("📭 Список задач пуст");
-            return;
-        }
+/// Вывод страницы списка задач
+fn print_list(todo: &ToDolist, filtr: &FiltrSpiska, sort: Sortirovka, page: usize) {
+    if todo.zadaniey.is_empty() {
+        println!("📭 Список задач пуст");
+        return;
+    }
+    let stranitsa = todo.page(filtr, sort, page);
 
-        // Рассчёт прогресса выполнения
-        let total = self.zadaniey.len();
-        let done = self.zadaniey.iter().filter(|t| t.done).count();
-        let progress = (done as f32 / total as f32) * 100.0;
+    // Красивое форматирование вывода
+    println!("📋 Список задач (выполнено: {:.1}%):", stranitsa.progress);
+    println!("⚠️ просрочено: {}", stranitsa.overdue);
+    if stranitsa.zadaniey.is_empty() {
+        println!("📭 Нет подходящих задач");
+    }
+    let today = todo.today();
+    for zadanie in &stranitsa.zadaniey {
+        println!("{}", zadanie.colored_line(today, color_out()));
+    }
+    if stranitsa.pages > 1 {
+        println!("📄 страница {} из {}", stranitsa.page, stranitsa.pages);
+    }
+}
 
-        // Красивое форматирование вывода
-        println!("📋 Список задач (выполнено: {:.1}%):", progress);
-        for zadanie in &self.zadaniey {
-            let status = if zadanie.done { "✓" } else { " " }; // Галочка для выполненных
-            let due_info = match &zadanie.due_date {
-                Some(date) => format!(" [срок: {}]", date), // Показываем срок если есть
-                None => String::new(),
-            };
+/// Подробный вывод одной задачи
+fn print_details(zadanie: &Zadanie) {
+    let status = if zadanie.done {
+        "выполнена"
+    } else {
+        "не выполнена"
+    };
+    println!("🔖 Задача {}: {}", zadanie.id, zadanie.text);
+    println!("   Статус: {}", status);
+    match &zadanie.raw_created_at {
+        Some(raw) => println!("   Создана: неизвестно («{}»)", raw),
+        None => println!(
+            "   Создана: {}",
+            zadanie.created_at.format("%Y-%m-%d %H:%M:%S")
+        ),
+    }
+    if let Some(completed) = &zadanie.completed_at {
+        println!("   Выполнена: {}", completed.format("%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(raw) = &zadanie.raw_completed_at {
+        println!("   Выполнена: неизвестно («{}»)", raw);
+    }
+    if let Some(due) = &zadanie.due_date {
+        println!("   Срок: {}", due);
+    }
+    println!("   Приоритет: {}", zadanie.priority.label());
+    if let Some(rule) = &zadanie.recur {
+        println!("   Повтор: {}", rule_label(rule));
+    }
+    if let Some(source) = &zadanie.istochnik {
+        println!("   Источник: {}", source);
+    }
+    if let Some(until) = &zadanie.otlozheno_do {
+        println!("   Отложена до: {}", until);
+    }
+    if let Some(note) = &zadanie.note {
+        println!("   📝 Заметка: {}", note);
+    }
+}
+
+/// Вывод сводной статистики
+fn print_stats(stats: &Statistika) {
+    println!("📊 Статистика:");
+    println!("   Всего задач: {}", stats.total);
+    println!("   Выполнено: {}", stats.completed);
+    println!("   Не выполнено: {}", stats.total - stats.completed);
+    println!("   Просрочено: {}", stats.overdue);
+    println!("   Со сроком: {}", stats.with_due);
+    match stats.average_days {
+        Some(average) => println!("   Среднее время выполнения: {:.1} дн.", average),
+        None => println!("   Среднее время выполнения: нет данных"),
+    }
+}
+
+/// Вывод прогноза завершения
+fn print_forecast(prognoz: &Prognoz) {
+    match prognoz {
+        Prognoz::VsyoVypolneno => println!("🎉 Невыполненных задач нет"),
+        Prognoz::MaloDannykh => println!(
+            "📉 Прогноз: недостаточно данных (нет выполненных задач за {} дней)",
+            FORECAST_DAYS
+        ),
+        Prognoz::Zavershenie {
+            per_day,
+            pending,
+            days,
+            date,
+        } => {
             println!(
-                "{:3} [{}] {}{}", // Формат: "ID [✓] Текст [срок: ...]"
-                zadanie.id, status, zadanie.text, due_info
+                "📈 Скорость: {:.2} задач в день (за {} дней)",
+                per_day, FORECAST_DAYS
+            );
+            println!(
+                "🏁 Осталось задач: {}, примерно дней: {}, ожидаемое завершение: {}",
+                pending,
+                days,
+                date.format("%Y-%m-%d")
             );
         }
     }
+}
 
-    /// Отметка задачи как выполненной по ID
-    fn complete(&mut self, id: usize) -> Result<(), String> {
-        match self.zadaniey.iter_mut().find(|t| t.id == id) {
-            Some(zadanie) => {
-                zadanie.complete();
-                Ok(println!("👍 Задача {} выполнена", id))
-            }
-            None => Err(format!("❌ Задача с ID {} не найдена", id)),
+/// Вывод повторяющихся задач с правилом и следующим сроком
+fn print_recurring(povtory: &[(&Zadanie, Option<NaiveDate>)]) {
+    if povtory.is_empty() {
+        println!("📭 Повторяющихся задач нет");
+        return;
+    }
+    println!("🔁 Повторяющиеся задачи:");
+    for (zadanie, next) in povtory {
+        let rule = zadanie.recur.as_deref().unwrap_or_default();
+        let next = match next {
+            Some(date) => date.format("%Y-%m-%d").to_string(),
+            None => "неизвестно".to_string(),
+        };
+        println!(
+            "{} ({}, следующий срок: {})",
+            zadanie.format_line(),
+            rule_label(rule),
+            next
+        );
+    }
+}
+
+/// Вывод задач, текст которых содержит подстроку
+fn print_search(todo: &ToDolist, query: &str) {
+    let found = todo.search_text(query);
+    if found.is_empty() {
+        println!("🔍 По запросу «{}» ничего не найдено", query);
+        return;
+    }
+    println!("🔍 Найдено задач: {}", found.len());
+    let today = todo.today();
+    for zadanie in found {
+        println!("{}", zadanie.colored_line(today, color_out()));
+    }
+}
+
+/// Язык меню и подсказок: английский вместо русского
+static ENGLISH: AtomicBool = AtomicBool::new(false);
+
+fn is_english() -> bool {
+    ENGLISH.load(Ordering::Relaxed)
+}
+
+/// Выбор языка: флаг --lang en|ru, иначе TODO_LANG или LANG (например, en_US.UTF-8)
+fn english_from_args_and_env() -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    let lang = match args.iter().position(|arg| arg == "--lang") {
+        Some(index) => args.get(index + 1).cloned().unwrap_or_default(),
+        None => std::env::var("TODO_LANG")
+            .or_else(|_| std::env::var("LANG"))
+            .unwrap_or_default(),
+    };
+    lang.starts_with("en")
+}
+
+/// Выбор резервной копии: номер из команды или из ответа на вопрос.
+/// Ok(None) — пользователь ничего не выбрал
+fn choose_backup(path: &Path, choice: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let mut backups = list_backups(path)
+        .map_err(|e| format!("❌ Не удалось прочитать резервные копии: {}", e))?;
+    if backups.is_empty() {
+        return Err("📭 Резервных копий нет".to_string());
+    }
+    backups.reverse(); // Свежие копии первыми
+
+    println!("🗄️ Резервные копии:");
+    for (number, backup) in backups.iter().enumerate() {
+        let name = backup.file_name().unwrap_or_default().to_string_lossy();
+        println!("{:>3}) {}", number + 1, name);
+    }
+    let answer = match choice {
+        Some(choice) => choice.to_string(),
+        None => match prompt("Номер копии (Enter — отмена): ") {
+            Some(answer) if !answer.is_empty() => answer,
+            _ => return Ok(None),
+        },
+    };
+    match answer.parse::<usize>() {
+        Ok(number) if (1..=backups.len()).contains(&number) => {
+            Ok(Some(backups.swap_remove(number - 1)))
         }
+        _ => Err("❌ Неверный номер копии".to_string()),
     }
+}
 
-    /// Удаление задачи по ID
-    fn remove(&mut self, id: usize) -> Result<(), String> {
-        let index = self.zadaniey.iter().position(|t| t.id == id);
-        match index {
-            Some(i) => {
-                self.zadaniey.remove(i);
-                Ok(println!("🗑️ Задача {} удалена", id))
-            }
-            None => Err(format!("❌ Задача с ID {} не найдена", id)),
+/// Копирование текста в системный буфер обмена через доступную утилиту
+fn copy_to_clipboard(text: &str) -> bool {
+    // Пробуем утилиты разных платформ по очереди
+    let candidates: [(&str, &[&str]); 5] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("clip", &[]),
+    ];
+    for (program, args) in candidates {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue; // Утилита не установлена
+        };
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+            None => false,
+        };
+        if written && child.wait().is_ok_and(|status| status.success()) {
+            return true;
         }
     }
+    false
 }
 
-/// Сохранение задач в JSON файл
-fn save_to_file(zadaniey: &[Zadanie]) -> io::Result<()> {
-    let json = serde_json::to_string(zadaniey)?; // Сериализация в JSON
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true) // Создать если не существует
-        .truncate(true) // Очистить если существует
-        .open("zadaniey.json")?; // Открыть файл
-    file.write_all(json.as_bytes())?; // Записать данные
-    Ok(())
+/// Предпросмотр задач перед массовым действием и запрос подтверждения
+fn preview_and_confirm(action: &str, zadaniey: &[&Zadanie], yes: bool) -> bool {
+    println!("🔎 {} — задач: {}", action, zadaniey.len());
+    for zadanie in zadaniey {
+        println!("{}", zadanie.format_line());
+    }
+    yes || confirm("Продолжить?")
 }
 
-/// Загрузка задач из JSON файла
-fn load_from_file() -> io::Result<Vec<Zadanie>> {
-    if !Path::new("zadaniey.json").exists() {
-        return Ok(Vec::new()); // Вернуть пустой вектор если файла нет
+/// Вопрос пользователю; None при конце ввода или ошибке чтения
+fn prompt(question: &str) -> Option<String> {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
     }
+}
 
-    let mut file = File::open("zadaniey.json")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?; // Чтение всего файла
-    let zadaniey = serde_json::from_str(&contents)?; // Парсинг JSON
-    Ok(zadaniey)
+/// Вопрос пользователю с ответом да/нет
+fn confirm(question: &str) -> bool {
+    match prompt(&format!("{} (д/н): ", question)) {
+        Some(answer) => matches!(answer.to_lowercase().as_str(), "д" | "да"),
+        None => false,
+    }
 }
 
-fn main() {
-    // Загрузка существующих задач или создание нового списка
-    let mut todo = match load_from_file() {
-        Ok(zadaniey) => {
-            // Восстанавливаем next_id как максимальный ID + 1
-            let next_id = zadaniey.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-            ToDolist { zadaniey, next_id }
+/// Интерактивный выбор задачи: ввод текста сужает список, номер выбирает задачу.
+/// Возвращает ID выбранной задачи
+fn pick_task(todo: &ToDolist) -> Option<usize> {
+    let mut query = String::new();
+    loop {
+        // Выбирать имеет смысл только среди невыполненных
+        let matches: Vec<&Zadanie> = todo
+            .search_text(&query)
+            .into_iter()
+            .filter(|t| !t.done)
+            .collect();
+        if matches.is_empty() {
+            println!("📭 Ничего не найдено по «{}»", query);
         }
-        Err(e) => {
-            eprintln!("⚠️ Ошибка загрузки: {}. Новый список создан.", e);
-            ToDolist::new()
+        for (number, zadanie) in matches.iter().enumerate() {
+            println!("{:>3}) {}", number + 1, zadanie.format_line());
+        }
+
+        let input = prompt("🔎 Номер, новый фильтр или пустая строка для выхода: ")?;
+        if input.is_empty() {
+            return None;
+        }
+        match input.parse::<usize>() {
+            Ok(number) if (1..=matches.len()).contains(&number) => {
+                return Some(matches[number - 1].id)
+            }
+            _ => query = input, // Всё остальное — новый текст фильтра
+        }
+    }
+}
+
+/// Команда JSON-режима: одна строка stdin — один объект
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum RpcRequest {
+    Add { text: String, due: Option<String> },
+    List,
+    Complete { id: usize },
+    Remove { id: usize },
+}
+
+/// Ответ JSON-режима: одна строка stdout на каждую команду
+#[derive(Debug, Default, Serialize)]
+struct RpcResponse<'a> {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tasks: Option<Vec<&'a Zadanie>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl RpcResponse<'_> {
+    fn error(message: String) -> Self {
+        RpcResponse {
+            ok: false,
+            error: Some(message),
+            ..Default::default()
         }
+    }
+}
+
+/// Выполнение одной команды JSON-режима; unsaved — последнее сохранение не удалось
+fn handle_rpc<'a>(
+    todo: &'a mut ToDolist,
+    path: &Path,
+    line: &str,
+    unsaved: &mut bool,
+) -> RpcResponse<'a> {
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return RpcResponse::error(format!("❌ Неверная команда: {}", e)),
     };
 
-    // Приветствие и текущая дата
-    println!("✨ ToDo менеджер v1.0");
-    println!("📅 Сегодня: {}", Local::now().format("%Y-%m-%d"));
+    // Мутирующие команды возвращают ID затронутой задачи
+    let changed = match request {
+        RpcRequest::Add { text, due: None } => todo.add(text, false),
+        RpcRequest::Add {
+            text,
+            due: Some(date),
+        } => todo
+            .add_with_date(text, &date, false)
+            .inspect(|&id| warn_if_overbooked(todo, id)),
+        RpcRequest::Complete { id } => todo.complete(id).map(|_| id),
+        RpcRequest::Remove { id } => todo.remove(id).map(|t| t.id).map_err(|e| e.to_string()),
+        RpcRequest::List => {
+            return RpcResponse {
+                ok: true,
+                tasks: Some(todo.visible_tasks(&FiltrSpiska::Vse, todo.sort)),
+                ..Default::default()
+            };
+        }
+    };
 
-    // Основной цикл программы
-    loop {
-        // Вывод меню команд
-        println!("\n📌 Команды:");
-        println!(" добавить <текст> - Добавить задачу");
-        println!(" добавить <текст> до <ГГГГ-ММ-ДД> - Добавить задачу со сроком");
-        println!(" список - Показать все задачи");
-        println!(" выполнить <ID> - Отметить задачу как выполненную");
-        println!(" удалить <ID> - Удалить задачу");
-        println!(" выход - Выйти из программы");
-        print!("➥ "); // Символ приглашения
-        io::Write::flush(&mut io::stdout()).unwrap(); // Сброс буфера вывода
+    match changed {
+        Ok(id) => match persist(todo, path) {
+            Ok(warnings) => {
+                *unsaved = false;
+                print_warnings(&warnings); // stdout занят ответами, предупреждения — в stderr
+                RpcResponse {
+                    ok: true,
+                    id: Some(id),
+                    ..Default::default()
+                }
+            }
+            Err(e) => {
+                *unsaved = true;
+                RpcResponse::error(format!("❌ Ошибка сохранения: {}", e))
+            }
+        },
+        Err(e) => RpcResponse::error(e),
+    }
+}
 
-        // Чтение пользовательского ввода
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let input = input.trim();
-
-        // Разбивка ввода на части для анализа команды
-        let parts: Vec<&str> = input.splitn(3, ' ').collect();
-
-        // Обработка команд
-        match parts[0] {
-            // Добавление задачи (с датой или без)
-            "добавить" if parts.len() >= 2 => {
-                if let Some((_cmd, rest)) = input.split_once(' ') {
-                    if let Some((text, date)) = rest.split_once(" до ") {
-                        // Формат: "добавить <текст> до <дата>"
-                        if let Err(e) = todo.add_with_date(text.to_string(), date) {
-                            eprintln!("{}", e);
-                        } else {
-                            save_to_file(&todo.zadaniey).unwrap();
+/// JSON-режим для программного управления: без меню и приглашений.
+/// Конец ввода — штатное завершение; автосохранение убирает main, если всё сохранено
+fn run_json(todo: &mut ToDolist, path: &Path, unsaved: &mut bool) {
+    for line in io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_rpc(todo, path, &line, unsaved);
+        // Ответ печатается всегда: при ошибке сериализации — с ok: false
+        match serde_json::to_string(&response) {
+            Ok(json) => println!("{}", json),
+            Err(e) => println!(
+                "{}",
+                serde_json::json!({
+                    "ok": false,
+                    "error": format!("❌ Ошибка сериализации ответа: {}", e),
+                })
+            ),
+        }
+    }
+}
+
+/// Сообщение о выполнении задачи и, если есть, о её следующем повторении
+fn print_completed(id: usize, next: Option<usize>) {
+    println!("👍 Задача {} выполнена", id);
+    if let Some(next) = next {
+        println!("🔁 Добавлено следующее повторение (ID: {})", next);
+    }
+}
+
+/// Английские синонимы команд: "add" → "добавить" и т.д.
+fn canonical_command(word: &str) -> &str {
+    match word {
+        "add" => "добавить",
+        "list" => "список",
+        "complete" => "выполнить",
+        "remove" => "удалить",
+        "exit" => "выход",
+        other => other,
+    }
+}
+
+/// Выполнение одной команды меню (общая часть интерактивного и разового режимов).
+/// Возвращает false, если пользователь завершает работу
+fn execute(todo: &mut ToDolist, path: &Path, input: &str, unsaved: &mut bool) -> bool {
+    // Разбивка ввода на части для анализа команды
+    let mut parts: Vec<&str> = input.splitn(3, ' ').collect();
+    parts[0] = canonical_command(parts[0]);
+    if parts[0] != "отменить" {
+        todo.remember(); // Снимок для отмены, если команда что-то изменит
+    }
+
+    // Обработка команд
+    match parts[0] {
+        // Добавление задачи (с датой или без)
+        "добавить" if parts.len() >= 2 => {
+            if let Some((_cmd, rest)) = input.split_once(' ') {
+                // "--force" последним словом снимает проверки срока и дубликатов
+                let (rest, force) = match rest.trim_end().rsplit_once(char::is_whitespace) {
+                    Some((head, "--force")) => (head.trim_end(), true),
+                    _ if rest.trim() == "--force" => ("", true),
+                    _ => (rest, false),
+                };
+                if let Some((text, date)) = rest.split_once(" до ") {
+                    // Формат: "добавить <текст> до <дата> [--force]"
+                    match todo.add_with_date(text.to_string(), date, force) {
+                        Ok(id) => {
+                            println!("✅ Задача с сроком добавлена (ID: {})", id);
+                            warn_if_overbooked(todo, id);
+                            *unsaved = !persist_or_warn(todo, path);
                         }
-                    } else {
-                        // Формат: "добавить <текст>"
-                        todo.add(rest.to_string());
-                        save_to_file(&todo.zadaniey).unwrap();
+                        Err(e) => print_error(e),
+                    }
+                } else {
+                    // Формат: "добавить <текст> [--force]"
+                    match todo.add(rest.to_string(), force) {
+                        Ok(id) => {
+                            println!("✅ Задача добавлена (ID: {})", id);
+                            *unsaved = !persist_or_warn(todo, path);
+                        }
+                        Err(e) => print_error(e),
                     }
                 }
             }
+        }
 
-            // Вывод списка задач
-            "список" => todo.list(),
+        // Вывод списка задач
+        "список" => match (parts.get(1), parts.get(2)) {
+            // Отложенные на будущее задачи в обычном списке не показываются
+            (None, _) => print_list(todo, &FiltrSpiska::Tekushchie, todo.sort, 1),
+            // Задачи, созданные указанным способом: "список источник <имя>"
+            (Some(&"источник"), Some(source)) => {
+                print_list(todo, &FiltrSpiska::Istochnik(source.to_string()), todo.sort, 1)
+            }
+            // Разовая сортировка: "список по <срок|приоритету|созданию|умно>"
+            (Some(&"по"), Some(word)) => match Sortirovka::parse(word) {
+                Some(sort) => print_list(todo, &FiltrSpiska::Tekushchie, sort, 1),
+                None => {
+                    print_error("❌ Неизвестная сортировка. Используйте: срок, приоритету, созданию, умно")
+                }
+            },
+            (Some(word), None) => match (word.parse::<usize>(), FiltrSpiska::parse(word)) {
+                // Страница списка: "список <номер>"
+                (Ok(page), _) => print_list(todo, &FiltrSpiska::Tekushchie, todo.sort, page),
+                // Фильтр по статусу: "список выполненные|активные|просроченные"
+                (_, Some(filtr)) => print_list(todo, &filtr, todo.sort, 1),
+                _ => print_error(
+                    "❌ Неизвестный фильтр. Используйте: выполненные, активные, просроченные, #метка"
+                ),
+            },
+            _ => print_error(
+                "❌ Неверный формат команды. Используйте: список [страница|фильтр] или список по <сортировка>"
+            ),
+        },
 
-            // Выполнение задачи по ID
-            "выполнить" if parts.len() > 1 => {
-                if let Ok(id) = parts[1].parse::<usize>() {
-                    if let Err(e) = todo.complete(id) {
-                        eprintln!("{}", e);
+        // Массовое выполнение: "выполнить всё [фильтр] [--yes]"
+        "выполнить" if matches!(parts.get(1), Some(&"всё") | Some(&"все")) => {
+            let options: Vec<&str> = parts
+                .get(2)
+                .map_or(Vec::new(), |rest| rest.split_whitespace().collect());
+            let yes = options.contains(&"--yes");
+            let filtr = match options.iter().find(|o| **o != "--yes") {
+                Some(word) => FiltrSpiska::parse(word),
+                None => Some(FiltrSpiska::Aktivnye),
+            };
+            match filtr {
+                Some(filtr) => {
+                    // Уже выполненные задачи в выборку не попадают
+                    let preview: Vec<&Zadanie> = todo
+                        .visible_tasks(&filtr, todo.sort)
+                        .into_iter()
+                        .filter(|t| !t.done)
+                        .collect();
+                    if preview.is_empty() {
+                        println!("📭 Нет подходящих невыполненных задач");
+                    } else if preview_and_confirm("Будут выполнены", &preview, yes)
+                    {
+                        let ids: Vec<usize> = preview.iter().map(|t| t.id).collect();
+                        let count = todo.complete_many(&ids);
+                        *unsaved = !persist_or_warn(todo, path);
+                        println!("👍 Выполнено задач: {}", count);
                     } else {
-                        save_to_file(&todo.zadaniey).unwrap();
+                        println!("↩️ Отменено");
                     }
-                } else {
-                    eprintln!("❌ Неверный ID задачи. Введите число.");
                 }
+                None => print_error(
+                    "❌ Неизвестный фильтр. Используйте: выполненные, активные, просроченные, #метка"
+                ),
             }
+        }
 
-            // Удаление задачи по ID
-            "удалить" if parts.len() > 1 => {
-                if let Ok(id) = parts[1].parse::<usize>() {
-                    if let Err(e) = todo.remove(id) {
-                        eprintln!("{}", e);
-                    } else {
-                        save_to_file(&todo.zadaniey).unwrap();
+        // Выполнение задачи по ID
+        "выполнить" if parts.len() > 1 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                match todo.complete(id) {
+                    Ok(next) => {
+                        print_completed(id, next);
+                        *unsaved = !persist_or_warn(todo, path);
                     }
+                    Err(e) => print_error(e),
+                }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
+            }
+        }
+
+        // Удаление задачи по ID
+        "удалить" if parts.len() > 1 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                // Сначала показываем задачу, затем спрашиваем; "--yes" или "да" — без вопроса
+                let yes = matches!(parts.get(2).map(|w| w.trim()), Some("--yes") | Some("да"));
+                match todo.find(id) {
+                    Ok(zadanie) => println!("{}", zadanie.format_line()),
+                    Err(e) => {
+                        print_error(e);
+                        return true;
+                    }
+                }
+                if !yes && !confirm("Удалить?") {
+                    println!("↩️ Отменено");
+                    return true;
+                }
+                match todo.remove(id) {
+                    Ok(zadanie) => {
+                        println!("🗑️ Задача {} «{}» удалена", zadanie.id, zadanie.text);
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    Err(e) => print_error(e),
+                }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
+            }
+        }
+
+        // Отложенные задачи
+        "отложенные" => print_list(todo, &FiltrSpiska::Otlozhennye, todo.sort, 1),
+
+        // Откладывание задачи: "отложить <ID> до <ГГГГ-ММ-ДД|завтра|+N>"
+        "отложить" if parts.len() == 3 => {
+            match (parts[1].parse::<usize>(), parts[2].strip_prefix("до ")) {
+                (Ok(id), Some(date)) => match todo.snooze(id, date.trim()) {
+                    Ok(()) => {
+                        if let Some(until) = todo.find(id).ok().and_then(|t| t.otlozheno_do) {
+                            println!("💤 Задача {} отложена до {}", id, until);
+                        }
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    Err(e) => print_error(e),
+                },
+                (Err(_), _) => print_error("❌ Неверный ID задачи. Введите число."),
+                (_, None) => {
+                    print_error("❌ Неверный формат команды. Используйте: отложить <ID> до <ГГГГ-ММ-ДД>")
+                }
+            }
+        }
+
+        // Подробности задачи
+        "показать" if parts.len() > 1 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                match todo.find(id) {
+                    Ok(zadanie) => print_details(zadanie),
+                    Err(e) => print_error(e),
+                }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
+            }
+        }
+
+        // Быстрый выбор задачи для выполнения
+        "выбрать" => {
+            if let Some(id) = pick_task(todo) {
+                let action = prompt("Действие: в — выполнить, и — изменить текст: ");
+                let result = match action.as_deref() {
+                    Some("в") => todo.complete(id).map(|next| print_completed(id, next)),
+                    Some("и") => match prompt("Новый текст: ") {
+                        Some(text) if !text.is_empty() => todo
+                            .edit(id, text)
+                            .map(|()| println!("✏️ Задача {} изменена", id)),
+                        _ => Err("❌ Текст задачи не может быть пустым".to_string()),
+                    },
+                    _ => Err("❌ Неизвестное действие".to_string()),
+                };
+                match result {
+                    Ok(()) => *unsaved = !persist_or_warn(todo, path),
+                    Err(e) => print_error(e),
+                }
+            }
+        }
+
+        // Возврат задачи в работу
+        "вернуть" if parts.len() > 1 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                match todo.reopen(id) {
+                    Ok(true) => {
+                        println!("↩️ Задача {} снова в работе", id);
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    Ok(false) => println!("ℹ️ Задача {} и так не выполнена", id),
+                    Err(e) => print_error(e),
+                }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
+            }
+        }
+
+        // Изменение текста задачи: "изменить <ID> <новый текст>"
+        "изменить" if parts.len() == 3 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                if let Err(e) = todo.edit(id, parts[2].to_string()) {
+                    print_error(e);
                 } else {
-                    eprintln!("❌ Неверный ID задачи. Введите число.");
+                    println!("✏️ Задача {} изменена", id);
+                    *unsaved = !persist_or_warn(todo, path);
                 }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
             }
+        }
 
-            // Выход из программы
-            "выход" => break,
+        // Копирование текста задачи в буфер обмена
+        "копировать" if parts.len() > 1 => {
+            if let Ok(id) = parts[1].parse::<usize>() {
+                match todo.find(id) {
+                    Ok(zadanie) => {
+                        if copy_to_clipboard(&zadanie.text) {
+                            println!("📋 Текст задачи {} скопирован в буфер обмена", id);
+                        } else {
+                            // Без буфера обмена (например, по SSH) просто показываем текст
+                            println!("⚠️ Буфер обмена недоступен, текст задачи {}:", id);
+                            println!("{}", zadanie.text);
+                        }
+                    }
+                    Err(e) => print_error(e),
+                }
+            } else {
+                print_error("❌ Неверный ID задачи. Введите число.");
+            }
+        }
 
-            // Пустая команда (просто нажатие Enter)
-            "" => continue,
+        // Экспорт сроков в календарь: "экспорт ics <файл>"
+        "экспорт" if parts.len() == 3 && parts[1] == "ics" => {
+            match export_ics(&todo.zadaniey, parts[2]) {
+                Ok(()) => {
+                    let count = todo
+                        .zadaniey
+                        .iter()
+                        .filter(|t| t.due_date.is_some())
+                        .count();
+                    println!(
+                        "📤 В {} экспортировано задач со сроком: {}",
+                        parts[2], count
+                    );
+                }
+                Err(e) => print_error(format!("❌ Ошибка экспорта: {}", e)),
+            }
+        }
 
-            // Неизвестная команда
-            _ => {
+        // Экспорт всех задач в таблицу: "экспорт <файл.csv>"
+        "экспорт" if parts.len() >= 2 && parts[1] != "ics" => {
+            if let Some((_cmd, file)) = input.split_once(' ') {
+                match export_csv(&todo.zadaniey, Path::new(file.trim())) {
+                    Ok(()) => println!(
+                        "📤 В {} экспортировано задач: {}",
+                        file.trim(),
+                        todo.zadaniey.len()
+                    ),
+                    Err(e) => print_error(format!("❌ Ошибка экспорта: {}", e)),
+                }
+            }
+        }
+
+        // Массовый сдвиг сроков: "сдвинуть <фильтр> <дни>"
+        "сдвинуть" if parts.len() == 3 => {
+            match (FiltrSpiska::parse(parts[1]), parts[2].parse::<i64>()) {
+                (Some(filtr), Ok(days)) => {
+                    let ids: Vec<usize> = todo
+                        .visible_tasks(&filtr, todo.sort)
+                        .into_iter()
+                        .filter(|t| !t.done)
+                        .map(|t| t.id)
+                        .collect();
+                    let from_today = shift_undated_from_today();
+                    let mut changed = 0;
+                    for id in ids {
+                        match todo.postpone(id, days, from_today) {
+                            Ok(true) => {
+                                changed += 1;
+                                warn_if_overbooked(todo, id);
+                            }
+                            Ok(false) => {}
+                            Err(e) => print_error(e),
+                        }
+                    }
+                    if changed > 0 {
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    println!("📆 Сроки сдвинуты у задач: {}", changed);
+                }
+                (None, _) => print_error(
+                    "❌ Неизвестный фильтр. Используйте: выполненные, активные, просроченные, #метка"
+                ),
+                (_, Err(_)) => print_error("❌ Сдвиг должен быть целым числом дней"),
+            }
+        }
+
+        // Утренний перенос: "перенести вчера"
+        "перенести" if parts.get(1) == Some(&"вчера") => {
+            let moved = todo.carry_over();
+            if let Some(&last) = moved.last() {
+                *unsaved = !persist_or_warn(todo, path);
+                println!("🌅 Перенесено на сегодня задач: {}", moved.len());
+                warn_if_overbooked(todo, last); // Остальные перенесённые уже на сегодня
+            } else {
+                println!("🌅 Вчерашних невыполненных задач нет");
+            }
+        }
+
+        // Задачи с меткой: "тег <имя>"
+        "тег" if parts.len() > 1 => {
+            let tag = parts[1].trim_start_matches('#').to_lowercase();
+            print_list(todo, &FiltrSpiska::Teg(tag), todo.sort, 1);
+        }
+
+        // Отмена последнего изменившего задачи действия
+        "отменить" => {
+            if todo.undo() {
+                *unsaved = !persist_or_warn(todo, path);
+                println!("↩️ Последнее действие отменено");
+            } else {
+                println!("↩️ Отменять нечего");
+            }
+        }
+
+        // Заметка к задаче: "заметка <ID> <текст|нет>"
+        "заметка" if parts.len() == 3 => match parts[1].parse::<usize>() {
+            Ok(id) => {
+                let note = parts[2].trim();
+                if note.is_empty() {
+                    print_error("❌ Текст заметки не может быть пустым (чтобы удалить — «нет»)");
+                    return true;
+                }
+                let note = if note == "нет" { None } else { Some(note) };
+                match todo.set_note(id, note) {
+                    Ok(()) => {
+                        match note {
+                            Some(_) => println!("📝 Заметка к задаче {} сохранена", id),
+                            None => println!("📝 Заметка к задаче {} удалена", id),
+                        }
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    Err(e) => print_error(e),
+                }
+            }
+            Err(_) => print_error("❌ Неверный ID задачи. Введите число."),
+        },
+
+        // Срок существующей задачи: "срок <ID> <ГГГГ-ММ-ДД|нет>"
+        "срок" if parts.len() == 3 => match parts[1].parse::<usize>() {
+            Ok(id) => {
+                let date = parts[2].trim();
+                let date = if date == "нет" { None } else { Some(date) };
+                match todo.set_due_date(id, date) {
+                    Ok(()) => {
+                        warn_if_overbooked(todo, id);
+                        // Печатаем уже разобранную дату: "+3" превращается в число
+                        match todo.find(id).ok().and_then(|t| t.due_date) {
+                            Some(due) => println!("📅 Срок задачи {} установлен: {}", id, due),
+                            None => println!("📅 Срок задачи {} снят", id),
+                        }
+                        *unsaved = !persist_or_warn(todo, path);
+                    }
+                    Err(e) => print_error(e),
+                }
+            }
+            Err(_) => print_error("❌ Неверный ID задачи. Введите число."),
+        },
+
+        // Возврат к резервной копии: "восстановить [номер]"
+        "восстановить" => match choose_backup(path, parts.get(1).copied()) {
+            Ok(Some(backup)) => match load_from_file(&backup) {
+                Ok(zadaniey) => {
+                    todo.restore(zadaniey);
+                    *unsaved = !persist_or_warn(todo, path);
+                    println!("♻️ Задачи восстановлены из {}", backup.display());
+                }
+                Err(e) => print_error(format!("❌ Не удалось прочитать копию: {}", e)),
+            },
+            Ok(None) => println!("↩️ Отменено"),
+            Err(e) => print_error(e),
+        },
+
+        // Удаление всех выполненных задач
+        "очистить" => {
+            let count = todo.clear_completed();
+            if count > 0 {
+                *unsaved = !persist_or_warn(todo, path);
+                println!("🧹 Удалено выполненных задач: {}", count);
+            } else {
+                println!("🧹 Выполненных задач нет, удалять нечего");
+            }
+        }
+
+        // Проверка целостности данных
+        "проверка" => {
+            // Нераспознанные отметки исправить нечем, их только показываем
+            let unparsed = todo.unparsed_dates();
+            if !unparsed.is_empty() {
+                println!("⚠️ Нераспознанные даты — задач: {}", unparsed.len());
+                for zadanie in &unparsed {
+                    for (field, raw) in zadanie.raw_dates() {
+                        println!("{} ({}: «{}»)", zadanie.format_line(), field, raw);
+                    }
+                }
+            }
+            let inverted = todo.check_timestamps();
+            if inverted.is_empty() {
+                if unparsed.is_empty() {
+                    println!("✅ Проблем не найдено");
+                }
+            } else {
+                let ids: Vec<usize> = inverted.iter().map(|t| t.id).collect();
+                if preview_and_confirm(
+                    "Выполнены раньше, чем созданы (время выполнения станет равным времени создания)",
+                    &inverted,
+                    false,
+                ) {
+                    let count = todo.fix_completed_before_created(&ids);
+                    *unsaved = !persist_or_warn(todo, path);
+                    println!("🛠️ Исправлено задач: {}", count);
+                }
+            }
+        }
+
+        // Поиск по всем полям задачи: "найти везде <слово>"
+        "найти" if parts.len() == 3 && parts[1] == "везде" => {
+            let found = todo.search_all(parts[2]);
+            if found.is_empty() {
+                println!("🔍 Ничего не найдено");
+            } else {
+                println!("🔍 Найдено совпадений: {}", found.len());
+                for (zadanie, field) in found {
+                    println!("{} ← {}", zadanie.format_line(), field);
+                }
+            }
+        }
+
+        // Прогноз завершения по скорости выполнения
+        "прогноз" => print_forecast(&todo.forecast()),
+
+        // Сводные показатели по задачам
+        "статистика" => print_stats(&todo.stats()),
+
+        // Поиск по тексту задачи: "найти <подстрока>"
+        "найти" if parts.len() > 1 => {
+            if let Some((_cmd, query)) = input.split_once(' ') {
+                print_search(todo, query.trim());
+            }
+        }
+
+        // Сводка повторяющихся задач
+        "повторы" => print_recurring(&todo.recurring()),
+
+        // Выход из программы
+        "выход" => return false,
+
+        // Пустая команда (просто нажатие Enter)
+        "" => {}
+
+        // Неизвестная команда
+        _ => {
+            if is_english() {
                 if parts[0] == "добавить" {
-                    eprintln!("❌ Неверный формат команды. Используйте: добавить <текст задачи> [до <ГГГГ-ММ-ДД>]");
+                    print_error("❌ Invalid command format. Use: add <task text> [до <YYYY-MM-DD>]");
                 } else {
-                    eprintln!("❌ Неизвестная команда. Доступные команды: добавить, список, выполнить, удалить, выход");
+                    print_error("❌ Unknown command. Available commands: add, list, complete, remove, exit, показать, заметка, отложить, срок, отложенные, тег, найти, прогноз, статистика, повторы, вернуть, выбрать, изменить, сдвинуть, перенести, очистить, отменить, восстановить, проверка, копировать, экспорт");
                 }
+            } else if parts[0] == "добавить" {
+                print_error("❌ Неверный формат команды. Используйте: добавить <текст задачи> [до <ГГГГ-ММ-ДД>]");
+            } else {
+                print_error("❌ Неизвестная команда. Доступные команды: добавить, список, показать, заметка, отложить, срок, отложенные, тег, найти, прогноз, статистика, повторы, выполнить, вернуть, выбрать, изменить, сдвинуть, перенести, удалить, очистить, отменить, восстановить, проверка, копировать, экспорт, выход");
+            }
+        }
+    }
+    true
+}
+
+/// Вывод меню команд
+fn print_menu() {
+    if is_english() {
+        print_menu_en();
+        return;
+    }
+    println!("\n📌 Команды:");
+    println!(" добавить <текст> [--force] - Добавить задачу (--force — даже если такая уже есть)");
+    println!(" добавить <текст> до <ГГГГ-ММ-ДД|сегодня|завтра|+N> [--force] - Добавить задачу со сроком (--force — срок в прошлом или дубликат)");
+    println!(" добавить !высокий|!низкий <текст> - Добавить задачу с приоритетом");
+    println!(" добавить <текст> #метка - Добавить задачу с меткой");
+    println!(
+        " добавить <текст> каждый день|каждую неделю|каждый месяц - Добавить повторяющуюся задачу"
+    );
+    println!(" список [страница] - Показать все задачи (по 20 на странице)");
+    println!(" список по <срок|приоритету|созданию|умно> - Показать задачи в выбранном порядке");
+    println!(
+        " список <выполненные|активные|просроченные|#метка> - Показать задачи по статусу или метке"
+    );
+    println!(" список источник <имя> - Показать задачи по способу создания");
+    println!(" показать <ID> - Показать подробности задачи");
+    println!(" заметка <ID> <текст|нет> - Добавить, заменить или удалить заметку к задаче");
+    println!(" отложить <ID> до <ГГГГ-ММ-ДД> - Скрыть задачу из списка до даты");
+    println!(" срок <ID> <ГГГГ-ММ-ДД|нет> - Установить или снять срок задачи");
+    println!(" отложенные - Показать отложенные задачи");
+    println!(" тег <имя> - Показать задачи с меткой");
+    println!(" найти <текст> - Найти задачи по тексту");
+    println!(" найти везде <слово> - Искать слово во всех полях задач");
+    println!(" прогноз - Оценить, когда будут выполнены все задачи");
+    println!(" статистика - Показать сводные показатели по задачам");
+    println!(" повторы - Показать повторяющиеся задачи и их следующие сроки");
+    println!(" выполнить <ID> - Отметить задачу как выполненную");
+    println!(" вернуть <ID> - Вернуть выполненную задачу в работу");
+    println!(" выбрать - Найти задачу по тексту, чтобы выполнить или изменить её");
+    println!(" изменить <ID> <текст> - Изменить текст задачи");
+    println!(" выполнить всё [фильтр] [--yes] - Выполнить все подходящие задачи");
+    println!(" сдвинуть <фильтр> <дни> - Сдвинуть сроки подходящих задач");
+    println!(" перенести вчера - Перенести вчерашние невыполненные задачи на сегодня");
+    println!(" удалить <ID> [--yes] - Удалить задачу (с подтверждением)");
+    println!(" очистить - Удалить все выполненные задачи");
+    println!(" отменить - Отменить последнее изменение");
+    println!(" восстановить [номер] - Загрузить задачи из резервной копии");
+    println!(" проверка - Найти ошибки в отметках времени");
+    println!(" копировать <ID> - Скопировать текст задачи в буфер обмена");
+    println!(" экспорт ics <файл> - Экспортировать сроки в календарь (.ics)");
+    println!(" экспорт <файл.csv> - Экспортировать все задачи в таблицу (.csv)");
+    println!(" выход - Выйти из программы");
+}
+
+/// Меню на английском: синонимы там, где они есть, остальные команды — как есть
+fn print_menu_en() {
+    println!("\n📌 Commands:");
+    println!(" add <text> [--force] - Add a task (--force allows a duplicate)");
+    println!(" add <text> до <YYYY-MM-DD|сегодня|завтра|+N> [--force] - Add a task with a due date (--force allows a past date or a duplicate)");
+    println!(" add !высокий|!низкий <text> - Add a task with a priority");
+    println!(" add <text> #tag - Add a tagged task");
+    println!(" add <text> каждый день|каждую неделю|каждый месяц - Add a recurring task");
+    println!(" list [page] - Show all tasks (20 per page)");
+    println!(" list по <срок|приоритету|созданию|умно> - Show tasks in the chosen order");
+    println!(" list <выполненные|активные|просроченные|#tag> - Show tasks by status or tag");
+    println!(" list источник <name> - Show tasks by how they were created");
+    println!(" показать <ID> - Show task details");
+    println!(" заметка <ID> <text|нет> - Set, replace or remove a task note");
+    println!(" отложить <ID> до <YYYY-MM-DD> - Hide a task until a date");
+    println!(" срок <ID> <YYYY-MM-DD|нет> - Set or clear a task's due date");
+    println!(" отложенные - Show snoozed tasks");
+    println!(" тег <name> - Show tasks with a tag");
+    println!(" найти <text> - Search tasks by text");
+    println!(" найти везде <word> - Search all task fields");
+    println!(" прогноз - Estimate when all tasks will be done");
+    println!(" статистика - Show task statistics");
+    println!(" повторы - Show recurring tasks and their next due dates");
+    println!(" complete <ID> - Mark a task as done");
+    println!(" вернуть <ID> - Reopen a completed task");
+    println!(" выбрать - Find a task by text to complete or edit it");
+    println!(" изменить <ID> <text> - Change task text");
+    println!(" complete всё [filter] [--yes] - Complete all matching tasks");
+    println!(" сдвинуть <filter> <days> - Shift due dates of matching tasks");
+    println!(" перенести вчера - Move yesterday's pending tasks to today");
+    println!(" remove <ID> [--yes] - Remove a task (asks for confirmation)");
+    println!(" очистить - Remove all completed tasks");
+    println!(" отменить - Undo the last change");
+    println!(" восстановить [number] - Load tasks from a backup");
+    println!(" проверка - Find timestamp errors");
+    println!(" копировать <ID> - Copy task text to the clipboard");
+    println!(" экспорт ics <file> - Export due dates to a calendar (.ics)");
+    println!(" экспорт <file.csv> - Export all tasks to a spreadsheet (.csv)");
+    println!(" exit - Quit");
+}
+
+/// Интерактивный режим: приветствие и цикл меню.
+/// Возвращает false, если чтение ввода завершилось ошибкой
+fn run_interactive(todo: &mut ToDolist, path: &Path, unsaved: &mut bool) -> bool {
+    // Приветствие и текущая дата
+    println!("✨ ToDo менеджер v1.0");
+    println!("📅 Сегодня: {}", todo.clock.now().format("%Y-%m-%d"));
+    if todo.ephemeral {
+        println!("🧪 Режим без сохранения: изменения не будут записаны на диск");
+    }
+
+    // Основной цикл программы
+    loop {
+        print_menu();
+        print!("➥ "); // Символ приглашения
+        let _ = io::stdout().flush(); // Сброс буфера вывода
+
+        // Чтение пользовательского ввода; конец ввода — штатный выход
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) => {
+                println!();
+                return true;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                print_error(format!("❌ Ошибка чтения ввода: {}", e));
+                return false;
+            }
+        }
+
+        if !execute(todo, path, input.trim(), unsaved) {
+            return true;
+        }
+    }
+}
+
+/// Команда из аргументов запуска (без служебных флагов), если она есть
+fn command_from_args() -> Option<String> {
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-save" | "--json" | "--force-unlock" | "--no-color" => {}
+            "--file" | "--lang" => {
+                args.next(); // Пропускаем значение флага
+            }
+            _ => words.push(arg),
+        }
+    }
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+fn main() {
+    // Режим без сохранения: флаг --no-save или TODO_EPHEMERAL=1
+    let ephemeral = std::env::args().any(|arg| arg == "--no-save")
+        || std::env::var("TODO_EPHEMERAL").is_ok_and(|v| v == "1");
+    ENGLISH.store(english_from_args_and_env(), Ordering::Relaxed);
+    init_colors();
+    let json_mode = std::env::args().any(|arg| arg == "--json");
+    let command = command_from_args();
+
+    let data_path = match data_path_from_args() {
+        Ok(path) => path,
+        Err(e) => {
+            print_error(e);
+            std::process::exit(2);
+        }
+    };
+
+    // Вторая копия программы затёрла бы изменения первой; без сохранения блокировка не нужна
+    let lock = if ephemeral {
+        None
+    } else {
+        let force = std::env::args().any(|arg| arg == "--force-unlock");
+        match Blokirovka::acquire(&data_path, force) {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                print_error(e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let clock = clock_from_env();
+
+    // Предлагаем восстановление, если прошлый сеанс завершился аварийно
+    // (в JSON-режиме и при запуске из скриптов вопросы неуместны)
+    let recovered = !json_mode
+        && command.is_none()
+        && !ephemeral
+        && autosave_is_newer(&data_path)
+        && confirm("⚠️ Найдено автосохранение после незавершённого сеанса. Восстановить?");
+    let source = if recovered {
+        autosave_path(&data_path)
+    } else {
+        data_path.clone()
+    };
+    let loaded = if ephemeral {
+        Ok(Vec::new()) // Без сохранения всегда начинаем с пустого списка
+    } else {
+        load_from_file(&source)
+    };
+
+    // Загрузка существующих задач или создание нового списка
+    let mut todo = match loaded {
+        Ok(zadaniey) => ToDolist::from_zadaniey(zadaniey, clock),
+        // Файл записан более новой версией: не переименовываем и не перезаписываем его
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+            print_error(format!("❌ Не удалось открыть {}: {}", source.display(), e));
+            drop(lock); // exit не вызывает деструкторы
+            std::process::exit(1);
+        }
+        // Файл есть, но не разбирается: убираем его в сторону, чтобы не затереть при сохранении
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => match salvage_corrupt(&source) {
+            Ok(moved) => {
+                eprintln!("🚨🚨🚨 Файл задач {} повреждён: {}", source.display(), e);
+                eprintln!(
+                    "🚨 Он сохранён как {}, начинаем с пустого списка",
+                    moved.display()
+                );
+                ToDolist::new(clock)
+            }
+            Err(err) => {
+                print_error(format!(
+                    "❌ Файл задач {} повреждён ({}), и его не удалось переименовать: {}",
+                    source.display(),
+                    e,
+                    err
+                ));
+                drop(lock); // exit не вызывает деструкторы
+                std::process::exit(1);
             }
+        },
+        // Файл есть, но прочитать его нельзя (например, нет прав): пустой список затёр бы его
+        Err(e) => {
+            print_error(format!(
+                "❌ Не удалось прочитать {}: {}",
+                source.display(),
+                e
+            ));
+            drop(lock); // exit не вызывает деструкторы
+            std::process::exit(1);
         }
+    };
+
+    if !todo.unparsed_dates().is_empty() {
+        eprintln!("⚠️ У некоторых задач не распознаны даты, подробности — в «проверка»");
+    }
+
+    todo.ephemeral = ephemeral;
+    todo.reminder_file = std::env::var_os("TODO_REMINDER_FILE").map(PathBuf::from);
+    todo.sort = sort_from_env();
+    todo.max_per_day = max_per_day_from_env();
+    todo.page_size = page_size_from_env();
+
+    // Напоминания обновляются сразу при запуске: за ночь задачи могли стать просроченными
+    if let Err(e) = write_reminders(&todo) {
+        eprintln!("⚠️ Не удалось записать файл напоминаний: {}", e);
+    }
+
+    if recovered {
+        match save_to_file(&data_path, &todo.zadaniey) {
+            Ok(warnings) => {
+                print_warnings(&warnings);
+                println!("♻️ Задачи восстановлены из автосохранения");
+            }
+            Err(e) => eprintln!("⚠️ Задачи восстановлены, но не сохранены в файл: {}", e),
+        }
+    }
+
+    let mut unsaved = false; // Последнее сохранение не удалось
+    let mut io_failed = false; // Ввод оборвался из-за ошибки
+    match command {
+        _ if json_mode => {
+            todo.istochnik = ISTOCHNIK_RPC;
+            run_json(&mut todo, &data_path, &mut unsaved);
+        }
+        // Разовый режим: одна команда из аргументов, затем выход
+        Some(command) => {
+            todo.istochnik = ISTOCHNIK_SKRIPT;
+            execute(&mut todo, &data_path, &command, &mut unsaved);
+        }
+        None => io_failed = !run_interactive(&mut todo, &data_path, &mut unsaved),
+    }
+
+    if unsaved {
+        // Автосохранение оставляем: при следующем запуске его можно будет восстановить
+        eprintln!("⚠️ Последние изменения не удалось сохранить в файл");
+    } else if !ephemeral {
+        let _ = std::fs::remove_file(autosave_path(&data_path)); // Сеанс завершён штатно
+    }
+    if io_failed || unsaved {
+        drop(lock); // exit не вызывает деструкторы
+        std::process::exit(1);
     }
 }